@@ -24,3 +24,16 @@
     ]) @function
   (#set! "tag" "zig-test")
 )
+
+; build steps declared in build.zig, e.g. b.step("docs", "Generate documentation")
+(
+  (call_expression
+    function: (field_expression
+      member: (identifier) @_step)
+    [
+      (_ . (string) @run @zig_build_step)
+      ("(" . (string) @run @zig_build_step)
+    ])
+  (#eq? @_step "step")
+  (#set! "tag" "zig-build-step")
+)
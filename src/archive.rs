@@ -0,0 +1,416 @@
+use crate::{
+    error::{Error, Result},
+    verify::Integrity,
+};
+use serde::Deserialize;
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+use tar::EntryType;
+use zed_extension_api as zed;
+
+/// Formats release assets are shipped in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ArchiveFormat {
+    #[serde(rename = "tar.xz")]
+    TarXz,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "zip")]
+    Zip,
+    /// A single gzip compressed executable
+    #[serde(rename = "gz")]
+    Gzip,
+    /// A bare executable
+    #[serde(rename = "none")]
+    Uncompressed,
+}
+
+impl ArchiveFormat {
+    /// Setting values and file extensions of every supported format, for error messages
+    const SUPPORTED: &'static str =
+        "\"tar.xz\" (.tar.xz, .txz), \"tar.gz\" (.tar.gz, .tgz), \"zip\" (.zip), \"gz\" (.gz) \
+         and \"none\" (bare executables without or with an .exe extension)";
+
+    /// Infers the archive format from the file name at the end of a url
+    ///
+    /// File names without an extension are taken to be bare executables, version numbers
+    /// (`zls-0.14.0`) don't count as one.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url).to_ascii_lowercase();
+        let file_name = path.rsplit('/').next().unwrap_or(&path);
+
+        if file_name.ends_with(".tar.xz") || file_name.ends_with(".txz") {
+            Some(Self::TarXz)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if file_name.ends_with(".gz") {
+            Some(Self::Gzip)
+        } else {
+            match file_name.rsplit_once('.') {
+                Some((_, "exe")) => Some(Self::Uncompressed),
+                Some((_, extension)) if extension.chars().any(|c| c.is_ascii_alphabetic()) => None,
+                _ => Some(Self::Uncompressed),
+            }
+        }
+    }
+
+    /// Picks the format of a download, an explicitly configured one wins over the url
+    pub fn detect(url: &str, explicit: Option<Self>) -> Result<Self> {
+        explicit.or_else(|| Self::from_url(url)).ok_or_else(|| {
+            Error::InstallationFailed(format!(
+                "Can't tell the archive format of {url}. Supported formats are {}, set \"archive\" \
+                 to one of them if the url doesn't end in a matching extension",
+                Self::SUPPORTED
+            ))
+            .into()
+        })
+    }
+}
+
+/// Unpacks the archive at `archive` into `dest`
+///
+/// Single file formats are written to `dest/file_name`. Everything is streamed, `.tar.xz` is
+/// decompressed into a temporary tar next to the archive first.
+pub fn extract(archive: &Path, format: ArchiveFormat, dest: &Path, file_name: &str) -> Result<()> {
+    let error = |e: &dyn std::fmt::Display| {
+        Error::InstallationFailed(format!("Failed to extract {}: {e}", dest.display()))
+    };
+    let open = || fs::File::open(archive).map(BufReader::new);
+    let create_file = || {
+        let path = dest.join(file_name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::File::create(path)
+    };
+
+    match format {
+        ArchiveFormat::TarXz => {
+            let tar_path = archive.with_extension("tar");
+            let unpacked = open()
+                .and_then(|mut xz| {
+                    let mut tar = BufWriter::new(fs::File::create(&tar_path)?);
+                    lzma_rs::xz_decompress(&mut xz, &mut tar)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                    tar.flush()
+                })
+                .and_then(|_| unpack_tar(BufReader::new(fs::File::open(&tar_path)?), dest));
+            fs::remove_file(&tar_path).ok();
+            unpacked.map_err(|e| error(&e))?;
+        }
+        ArchiveFormat::TarGz => {
+            open()
+                .and_then(|gz| unpack_tar(flate2::bufread::GzDecoder::new(gz), dest))
+                .map_err(|e| error(&e))?;
+        }
+        ArchiveFormat::Gzip => {
+            open()
+                .and_then(|gz| {
+                    io::copy(&mut flate2::bufread::GzDecoder::new(gz), &mut create_file()?)
+                })
+                .map_err(|e| error(&e))?;
+        }
+        ArchiveFormat::Uncompressed => {
+            open()
+                .and_then(|mut file| io::copy(&mut file, &mut create_file()?))
+                .map_err(|e| error(&e))?;
+        }
+        ArchiveFormat::Zip => {
+            fs::File::open(archive)
+                .map_err(zip::result::ZipError::from)
+                .and_then(zip::ZipArchive::new)
+                .and_then(|mut archive| archive.extract(dest))
+                .map_err(|e| error(&e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the directories and regular files of a tar archive below `dest`
+///
+/// `tar::Archive::unpack` can't be used, it fails setting permissions on wasm. Executables are
+/// marked as such by the caller instead.
+fn unpack_tar(reader: impl Read, dest: &Path) -> io::Result<()> {
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let inside = |component| matches!(component, Component::Normal(_) | Component::CurDir);
+        if !path.components().all(inside) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("refusing to unpack {} outside of the destination", path.display()),
+            ));
+        }
+
+        let path = dest.join(path);
+        match entry.header().entry_type() {
+            EntryType::Directory => fs::create_dir_all(&path)?,
+            EntryType::Regular | EntryType::Continuous => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                io::copy(&mut entry, &mut fs::File::create(&path)?)?;
+            }
+            // Links and special files aren't needed to run anything we install.
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Downloads and unpacks an archive there is nothing to verify against
+///
+/// Zed unpacks everything but `.tar.xz`, which we have to do ourselves.
+pub fn download(
+    download_url: &str,
+    dest: &Path,
+    format: ArchiveFormat,
+    file_name: &str,
+) -> Result<()> {
+    let (path, file_type) = match format {
+        ArchiveFormat::TarXz => {
+            return download_verified(download_url, dest, format, file_name, &Integrity::default())
+                .map(|_| ());
+        }
+        ArchiveFormat::TarGz => (dest.to_path_buf(), zed::DownloadedFileType::GzipTar),
+        ArchiveFormat::Zip => (dest.to_path_buf(), zed::DownloadedFileType::Zip),
+        ArchiveFormat::Gzip => (dest.join(file_name), zed::DownloadedFileType::Gzip),
+        ArchiveFormat::Uncompressed => {
+            (dest.join(file_name), zed::DownloadedFileType::Uncompressed)
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(Error::from)?;
+    }
+    zed::download_file(
+        download_url,
+        path.to_str().ok_or_else(|| Error::InstallationFailed("Invalid path".into()))?,
+        file_type,
+    )
+    .map_err(|e| Error::DownloadFailed(format!("Failed to download from {download_url}: {e}")))?;
+    Ok(())
+}
+
+/// Downloads an archive without unpacking it, verifies it and only then extracts it
+///
+/// The raw archive is removed again once it has been unpacked, its checksum is returned.
+/// Neither the archive nor its contents are ever held in memory as a whole.
+pub fn download_verified(
+    download_url: &str,
+    dest: &Path,
+    format: ArchiveFormat,
+    file_name: &str,
+    integrity: &Integrity,
+) -> Result<String> {
+    let archive_path = dest.join("download.archive");
+
+    zed::download_file(
+        download_url,
+        archive_path.to_str().ok_or_else(|| Error::InstallationFailed("Invalid path".into()))?,
+        zed::DownloadedFileType::Uncompressed,
+    )
+    .map_err(|e| Error::DownloadFailed(format!("Failed to download from {download_url}: {e}")))?;
+
+    let verified = fs::File::open(&archive_path)
+        .map_err(|e| Error::from(e).into())
+        .and_then(|archive| integrity.verify(BufReader::new(archive)))
+        .and_then(|checksum| extract(&archive_path, format, dest, file_name).map(|_| checksum));
+    fs::remove_file(&archive_path).ok();
+    if verified.is_err() {
+        fs::remove_dir_all(dest).ok();
+    }
+    verified
+}
+
+/// Finds the executable `file_name` somewhere below an extracted archive
+///
+/// Archives often wrap their contents in a `<name>-<platform>-<version>/` or `bin/` folder, so
+/// after the exact `dir/file_name` the tree is searched breadth first for a file with the same
+/// name. The shallowest match wins.
+pub fn find_executable(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let exact = dir.join(file_name);
+    if exact.is_file() {
+        return Some(exact);
+    }
+
+    let name = Path::new(file_name).file_name()?;
+    let mut pending = VecDeque::from([dir.to_path_buf()]);
+    while let Some(dir) = pending.pop_front() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries = entries.filter_map(|entry| entry.ok()).collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                pending.push_back(entry.path());
+            } else if file_type.is_file() && entry.file_name() == name {
+                return Some(entry.path());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn detects_formats_from_urls() {
+        for (url, format) in [
+            ("https://ziglang.org/zig-linux-x86_64-0.14.0.tar.xz", Some(ArchiveFormat::TarXz)),
+            ("https://example.com/zls.txz", Some(ArchiveFormat::TarXz)),
+            ("https://example.com/zls-x86_64-linux.tar.gz", Some(ArchiveFormat::TarGz)),
+            ("https://example.com/zls.TGZ", Some(ArchiveFormat::TarGz)),
+            ("https://example.com/zls-windows.zip?token=1#top", Some(ArchiveFormat::Zip)),
+            ("https://example.com/zls.gz", Some(ArchiveFormat::Gzip)),
+            ("https://example.com/zls.exe", Some(ArchiveFormat::Uncompressed)),
+            ("https://example.com/zls", Some(ArchiveFormat::Uncompressed)),
+            ("https://example.com/zls-0.14.0", Some(ArchiveFormat::Uncompressed)),
+            ("https://example.com/zls.tar.bz2", None),
+            ("https://example.com/zls.7z", None),
+        ] {
+            assert_eq!(ArchiveFormat::from_url(url), format, "{url}");
+        }
+    }
+
+    #[test]
+    fn explicit_format_wins() {
+        let url = "https://example.com/zls.bin2";
+        assert!(ArchiveFormat::detect(url, None).is_err());
+        assert_eq!(ArchiveFormat::detect(url, Some(ArchiveFormat::Zip)), Ok(ArchiveFormat::Zip));
+    }
+
+    #[test]
+    fn finds_executable_at_the_top() {
+        let dir = TempDir::new("top");
+        dir.touch("zls");
+        dir.touch("a/zls");
+        assert_eq!(find_executable(&dir.0, "zls"), Some(dir.0.join("zls")));
+    }
+
+    #[test]
+    fn finds_shallowest_nested_executable() {
+        let dir = TempDir::new("nested");
+        dir.touch("b/bin/deep/zls");
+        dir.touch("b/bin/zls");
+        dir.touch("c/zls");
+        dir.touch("a/README.md");
+        assert_eq!(find_executable(&dir.0, "zls"), Some(dir.0.join("c/zls")));
+    }
+
+    #[test]
+    fn breaks_ties_by_name() {
+        let dir = TempDir::new("ties");
+        dir.touch("zls-b/zls");
+        dir.touch("zls-a/zls");
+        assert_eq!(find_executable(&dir.0, "zls"), Some(dir.0.join("zls-a/zls")));
+    }
+
+    #[test]
+    fn matches_file_name_of_nested_paths() {
+        let dir = TempDir::new("path");
+        dir.touch("zls-linux/bin/zls");
+        assert_eq!(find_executable(&dir.0, "bin/zls"), Some(dir.0.join("zls-linux/bin/zls")));
+    }
+
+    /// Path longer than the 100 bytes a plain tar header has room for, like many in Zig's std
+    const LONG_PATH: &str =
+        "zls-linux/lib/std/zig/llvm/bitcode_writer/some_rather_long_directory_name/\
+         and_an_even_longer_file_name.zig";
+
+    /// Tar archive holding a `zls-linux/` folder with a `zls` executable and a long path in it
+    fn tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder.append_data(&mut header, "zls-linux/", io::empty()).unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o755);
+        header.set_size(4);
+        builder.append_data(&mut header, "zls-linux/zls", &b"\x7fELF"[..]).unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(2);
+        builder.append_data(&mut header, LONG_PATH, &b"//"[..]).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn unpacks_tar_xz_and_tar_gz() {
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut &tar()[..], &mut xz).unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(&tar()).unwrap();
+
+        for (name, data, format) in
+            [("xz", xz, ArchiveFormat::TarXz), ("gz", gz.finish().unwrap(), ArchiveFormat::TarGz)]
+        {
+            let dir = TempDir::new(name);
+            let archive = dir.0.join("download.archive");
+            fs::write(&archive, data).unwrap();
+            extract(&archive, format, &dir.0, "zls").unwrap();
+            assert_eq!(fs::read(dir.0.join("zls-linux/zls")).unwrap(), b"\x7fELF");
+            assert_eq!(fs::read(dir.0.join(LONG_PATH)).unwrap(), b"//");
+            assert!(!dir.0.join("download.tar").exists());
+        }
+    }
+
+    #[test]
+    fn writes_single_file_formats_to_file_name() {
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(b"\x7fELF").unwrap();
+
+        for (name, data, format) in [
+            ("single-gz", gz.finish().unwrap(), ArchiveFormat::Gzip),
+            ("bare", b"\x7fELF".to_vec(), ArchiveFormat::Uncompressed),
+        ] {
+            let dir = TempDir::new(name);
+            let archive = dir.0.join("download.archive");
+            fs::write(&archive, data).unwrap();
+            extract(&archive, format, &dir.0, "bin/zls").unwrap();
+            assert_eq!(fs::read(dir.0.join("bin/zls")).unwrap(), b"\x7fELF");
+        }
+    }
+
+    #[test]
+    fn refuses_paths_outside_of_the_destination() {
+        for path in ["../zls", "/tmp/zls"] {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[.. path.len()].copy_from_slice(path.as_bytes());
+            header.set_size(0);
+            header.set_cksum();
+            let mut builder = tar::Builder::new(Vec::new());
+            builder.append(&header, io::empty()).unwrap();
+
+            let dir = TempDir::new("outside");
+            let data = builder.into_inner().unwrap();
+            assert!(unpack_tar(&data[..], &dir.0).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn ignores_directories_and_missing_files() {
+        let dir = TempDir::new("missing");
+        fs::create_dir_all(dir.0.join("zls")).unwrap();
+        dir.touch("bin/zig");
+        assert_eq!(find_executable(&dir.0, "zls"), None);
+        assert_eq!(find_executable(&dir.0.join("nope"), "zls"), None);
+    }
+}
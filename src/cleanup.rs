@@ -0,0 +1,132 @@
+use crate::util::compare_versions;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Number of installs per provider kept around when nothing else is configured
+pub const DEFAULT_KEEP_VERSIONS: usize = 3;
+
+/// Removes old `<name>-<version>` install directories from the extension directory
+///
+/// * the `keep` newest versions always stay
+/// * directories containing one of the `in_use` binaries are never touched
+///
+/// Cleanup is best effort, directories that can't be removed are left for the next run.
+pub fn remove_stale_installs(name: &str, keep: usize, in_use: &[&Path]) {
+    remove_stale(Path::new("."), &format!("{name}-"), keep, in_use, |_| true);
+}
+
+/// Removes old `zig-<version>` toolchain directories, keeping them like language servers
+///
+/// Only versions starting with a digit count, so a custom language server whose name starts
+/// with `zig-` is left alone.
+pub fn remove_stale_toolchains(keep: usize, in_use: &[&Path]) {
+    remove_stale(Path::new("."), "zig-", keep, in_use, is_toolchain_version);
+}
+
+fn is_toolchain_version(version: &str) -> bool {
+    version.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Removes stale `<prefix><version>` directories below `root`, `in_use` is relative to it
+fn remove_stale(
+    root: &Path,
+    prefix: &str,
+    keep: usize,
+    in_use: &[&Path],
+    is_version: impl Fn(&str) -> bool,
+) {
+    let protected = in_use.iter().filter_map(|path| install_dir(path)).collect::<Vec<_>>();
+
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let mut installs = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|entry| {
+            let dir = entry.file_name().into_string().ok()?;
+            let version = dir.strip_prefix(prefix).filter(|version| is_version(version))?;
+            Some((version.to_string(), PathBuf::from(dir)))
+        })
+        .collect::<Vec<_>>();
+
+    installs.sort_by(|(a, _), (b, _)| compare_versions(b, a));
+
+    for (_, dir) in installs.into_iter().skip(keep.max(1)) {
+        if !protected.contains(&dir) {
+            fs::remove_dir_all(root.join(dir)).ok();
+        }
+    }
+}
+
+/// Top level directory of the extension directory a binary was installed into
+fn install_dir(binary_path: &Path) -> Option<PathBuf> {
+    binary_path.components().find_map(|component| match component {
+        Component::Normal(dir) => Some(PathBuf::from(dir)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    fn dirs(names: &[&str], name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        for name in names {
+            dir.touch(&format!("{name}/bin"));
+        }
+        dir
+    }
+
+    fn remaining(dir: &TempDir) -> Vec<String> {
+        let mut names = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn keeps_newest_versions() {
+        let names = ["zls-0.12.0", "zls-0.14.0", "zls-0.13.0", "zls-0.15.0-dev.5+abc"];
+        let dir = dirs(&names, "keep");
+        dir.touch("zls-0.1.0");
+        remove_stale(&dir.0, "zls-", 2, &[], |_| true);
+        assert_eq!(remaining(&dir), ["zls-0.1.0", "zls-0.14.0", "zls-0.15.0-dev.5+abc"]);
+    }
+
+    #[test]
+    fn keeps_at_least_one_version() {
+        let dir = dirs(&["zls-0.13.0", "zls-0.14.0"], "keep-zero");
+        remove_stale(&dir.0, "zls-", 0, &[], |_| true);
+        assert_eq!(remaining(&dir), ["zls-0.14.0"]);
+    }
+
+    #[test]
+    fn keeps_installs_in_use() {
+        let dir = dirs(&["zls-0.12.0", "zls-0.13.0", "zls-0.14.0"], "in-use");
+        let in_use = [Path::new("./zls-0.12.0/zls-linux/zls")];
+        remove_stale(&dir.0, "zls-", 1, &in_use, |_| true);
+        assert_eq!(remaining(&dir), ["zls-0.12.0", "zls-0.14.0"]);
+    }
+
+    #[test]
+    fn toolchains_leave_custom_servers_alone() {
+        let dir = dirs(&["zig-0.12.0", "zig-0.13.0", "zig-0.14.0", "zig-lsp-1.0.0"], "zig");
+        remove_stale(&dir.0, "zig-", 1, &[], is_toolchain_version);
+        assert_eq!(remaining(&dir), ["zig-0.14.0", "zig-lsp-1.0.0"]);
+    }
+
+    #[test]
+    fn servers_leave_each_other_alone() {
+        let names = ["zls-0.13.0", "zls-0.14.0", "zigscient-0.1.0", "zigscient-0.2.0"];
+        let dir = dirs(&names, "names");
+        remove_stale(&dir.0, "zls-", 1, &[], |_| true);
+        assert_eq!(remaining(&dir), ["zigscient-0.1.0", "zigscient-0.2.0", "zls-0.14.0"]);
+    }
+}
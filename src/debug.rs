@@ -0,0 +1,183 @@
+use crate::error::{Error, Result};
+use std::{fs, path::Path};
+use zed_extension_api::{
+    self as zed, serde_json, BuildTaskDefinition, BuildTaskDefinitionTemplatePayload,
+    DebugAdapterBinary, DebugConfig, DebugRequest, DebugScenario, DebugTaskDefinition,
+    LaunchRequest, StartDebuggingRequestArguments, StartDebuggingRequestArgumentsRequest,
+    TaskTemplate,
+};
+
+/// Debug adapter registered in `extension.toml`
+pub const ADAPTER_NAME: &str = "lldb-dap";
+
+/// Locator registered in `extension.toml`, turns Zig tasks into debug scenarios
+pub const LOCATOR_NAME: &str = "zig";
+
+/// Directory in the extension work dir debug builds are written to
+const DEBUG_DIR: &str = "debug";
+
+/// Starts `lldb-dap` (called `lldb-vscode` before LLVM 18) from the worktree's PATH
+pub fn adapter_binary(
+    config: DebugTaskDefinition,
+    user_provided_path: Option<String>,
+    worktree: &zed::Worktree,
+) -> Result<DebugAdapterBinary> {
+    let command = user_provided_path
+        .or_else(|| worktree.which("lldb-dap"))
+        .or_else(|| worktree.which("lldb-vscode"))
+        .ok_or_else(|| Error::Configuration {
+            message: "lldb-dap was not found in PATH".into(),
+            fix: "Install LLDB 18 or newer, set the adapter path in the debug settings or pick \
+                  the CodeLLDB adapter instead"
+                .into(),
+        })?;
+
+    let configuration = parse_config(&config.config)?;
+    Ok(DebugAdapterBinary {
+        command: Some(command),
+        arguments: Vec::new(),
+        envs: Vec::new(),
+        cwd: Some(worktree.root_path()),
+        connection: None,
+        request_args: StartDebuggingRequestArguments {
+            request: request_kind(&configuration)?,
+            configuration: config.config,
+        },
+    })
+}
+
+pub fn request_kind(config: &serde_json::Value) -> Result<StartDebuggingRequestArgumentsRequest> {
+    match config.get("request").and_then(|request| request.as_str()) {
+        Some("launch") => Ok(StartDebuggingRequestArgumentsRequest::Launch),
+        Some("attach") => Ok(StartDebuggingRequestArgumentsRequest::Attach),
+        request => Err(Error::Configuration {
+            message: format!("Invalid debug request {request:?}"),
+            fix: "Set \"request\" to \"launch\" or \"attach\"".into(),
+        }
+        .into()),
+    }
+}
+
+/// Translates a debug session started from Zed's UI into an lldb-dap configuration
+pub fn config_to_scenario(config: DebugConfig) -> Result<DebugScenario> {
+    let configuration = match config.request {
+        DebugRequest::Launch(launch) => {
+            let env = launch.envs.into_iter().map(|(key, value)| format!("{key}={value}"));
+            serde_json::json!({
+                "request": "launch",
+                "program": launch.program,
+                "args": launch.args,
+                "cwd": launch.cwd,
+                "env": env.collect::<Vec<_>>(),
+                "stopOnEntry": config.stop_on_entry.unwrap_or_default(),
+            })
+        }
+        DebugRequest::Attach(attach) => serde_json::json!({
+            "request": "attach",
+            "pid": attach.process_id,
+        }),
+    };
+
+    Ok(DebugScenario {
+        label: config.label,
+        adapter: config.adapter,
+        build: None,
+        config: configuration.to_string(),
+        tcp_connection: None,
+    })
+}
+
+/// First phase of the locator, swaps `zig run` and `zig test` for a build of the same file
+///
+/// The executable is emitted into the extension work dir so no project files are touched.
+/// `zig test` keeps its arguments, so a `--test-filter` narrows the binary down to the selected
+/// test. Tasks that don't build a single file are left to other locators.
+pub fn create_scenario(
+    task: TaskTemplate,
+    resolved_label: String,
+    debug_adapter_name: String,
+) -> Option<DebugScenario> {
+    if task.command != "zig" {
+        return None;
+    }
+    let (subcommand, file) = (task.args.first()?, task.args.get(1)?);
+    let args = match subcommand.as_str() {
+        "run" => {
+            let program = debug_binary(file, "").ok()?;
+            vec!["build-exe".into(), file.clone(), format!("-femit-bin={program}")]
+        }
+        "test" => {
+            let program = debug_binary(file, "-test").ok()?;
+            let mut args = vec!["test".into(), file.clone()];
+            args.extend(task.args[2 ..].iter().cloned());
+            args.extend(["--test-no-exec".into(), format!("-femit-bin={program}")]);
+            args
+        }
+        _ => return None,
+    };
+
+    let template = TaskTemplate {
+        label: format!("zig {}", args[.. 2].join(" ")),
+        command: task.command,
+        args,
+        env: task.env,
+        cwd: task.cwd,
+    };
+
+    Some(DebugScenario {
+        label: resolved_label,
+        adapter: debug_adapter_name,
+        build: Some(BuildTaskDefinition::Template(BuildTaskDefinitionTemplatePayload {
+            locator_name: Some(LOCATOR_NAME.into()),
+            template,
+        })),
+        config: serde_json::Value::Null.to_string(),
+        tcp_connection: None,
+    })
+}
+
+/// Second phase of the locator, launches what the build task emitted
+pub fn run_locator(build_task: TaskTemplate) -> Result<DebugRequest> {
+    let program = build_task
+        .args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("-femit-bin="))
+        .ok_or_else(|| format!("{} doesn't emit a binary to debug", build_task.label))?;
+
+    Ok(DebugRequest::Launch(LaunchRequest {
+        program: program.into(),
+        cwd: build_task.cwd,
+        args: Vec::new(),
+        envs: build_task.env,
+    }))
+}
+
+/// Absolute path the debug build of `file` is emitted to, `suffix` is appended to its stem
+fn debug_binary(file: &str, suffix: &str) -> Result<String> {
+    fs::create_dir_all(DEBUG_DIR).map_err(Error::from)?;
+
+    // The task isn't resolved yet, so the file may still be a variable.
+    let name = match file {
+        "$ZED_FILENAME" => "$ZED_STEM".to_string(),
+        file => Path::new(file)
+            .file_stem()
+            .map_or_else(|| file.to_string(), |stem| stem.to_string_lossy().into_owned()),
+    };
+    let name = match zed::current_platform().0 {
+        zed::Os::Windows => format!("{name}{suffix}.exe"),
+        _ => format!("{name}{suffix}"),
+    };
+
+    let dir = std::env::current_dir().map_err(Error::from)?.join(DEBUG_DIR);
+    Ok(dir.join(name).to_string_lossy().into_owned())
+}
+
+fn parse_config(config: &str) -> Result<serde_json::Value> {
+    serde_json::from_str(config).map_err(|e| {
+        Error::Configuration {
+            message: format!("Invalid debug configuration: {e}"),
+            fix: "The configuration has to be a JSON object".into(),
+        }
+        .into()
+    })
+}
@@ -0,0 +1,110 @@
+use zed_extension_api::serde_json::{self, Value};
+
+/// Number of single character insertions, deletions and substitutions turning `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0 ..= b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Candidates close enough to `input` to be a typo of it, closest first
+pub fn closest<'a>(input: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let input = input.to_lowercase();
+    let max_distance = (input.chars().count() / 3).max(1);
+
+    let mut matches = candidates
+        .iter()
+        .map(|&candidate| (edit_distance(&input, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|&(distance, _)| distance);
+    matches.into_iter().map(|(_, candidate)| candidate).take(3).collect()
+}
+
+/// Fix for an unknown `input`, naming the closest candidates or all of them if none is close
+pub fn did_you_mean(input: &str, candidates: &[&str]) -> String {
+    let quoted = |names: &[&str]| {
+        names.iter().map(|name| format!("\"{name}\"")).collect::<Vec<_>>().join(", ")
+    };
+
+    match closest(input, candidates).as_slice() {
+        [] => format!("Valid values are {}.", quoted(candidates)),
+        [name] => format!("Did you mean \"{name}\"?"),
+        names => format!("Did you mean one of {}?", quoted(names)),
+    }
+}
+
+/// `lsp.<id>.settings` snippet showing how `key` is set
+pub fn example(language_server_id: &str, key: &str, value: Value) -> String {
+    let snippet = serde_json::json!({
+        "lsp": { language_server_id: { "settings": { key: value } } }
+    });
+    serde_json::to_string_pretty(&snippet).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_edit_distance() {
+        for (a, b, distance) in [
+            ("", "", 0),
+            ("zls", "zls", 0),
+            ("", "zls", 3),
+            ("zls", "", 3),
+            ("provder", "provider", 1),
+            ("zlss", "zls", 1),
+            ("gihtub", "github", 2),
+            ("kitten", "sitting", 3),
+            ("zig_verison", "zig_version", 2),
+            ("ä", "a", 1),
+        ] {
+            assert_eq!(edit_distance(a, b), distance, "{a} -> {b}");
+            assert_eq!(edit_distance(b, a), distance, "{b} -> {a}");
+        }
+    }
+
+    #[test]
+    fn suggests_closest_candidates_first() {
+        let candidates = ["zls", "zls-stable", "zigscient", "custom"];
+        assert_eq!(closest("zlss", &candidates), ["zls"]);
+        assert_eq!(closest("ZigScent", &candidates), ["zigscient"]);
+        assert_eq!(closest("zls-stabel", &candidates), ["zls-stable"]);
+        assert!(closest("blah", &candidates).is_empty());
+
+        let keys = ["version", "versions", "offline"];
+        assert_eq!(closest("versio", &keys), ["version", "versions"]);
+    }
+
+    #[test]
+    fn names_suggestions_or_all_candidates() {
+        let candidates = ["github", "api"];
+        assert_eq!(did_you_mean("gihub", &candidates), "Did you mean \"github\"?");
+        assert_eq!(did_you_mean("ftp", &candidates), "Valid values are \"github\", \"api\".");
+        assert_eq!(
+            did_you_mean("versio", &["version", "versions"]),
+            "Did you mean one of \"version\", \"versions\"?"
+        );
+    }
+
+    #[test]
+    fn embeds_example_in_settings() {
+        let example = example("zls", "offline", true.into());
+        let example: Value = serde_json::from_str(&example).unwrap();
+        assert_eq!(
+            example,
+            serde_json::json!({ "lsp": { "zls": { "settings": { "offline": true } } } })
+        );
+    }
+}
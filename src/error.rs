@@ -12,6 +12,7 @@ pub enum Error {
     SerializationFailed(String),
     Settings(String),
     Configuration { message: String, fix: String },
+    VerificationFailed(String),
 }
 
 impl fmt::Display for Error {
@@ -31,6 +32,7 @@ impl fmt::Display for Error {
             Error::Configuration { message, fix } => {
                 write!(f, "Configuration error: {}. {}", message, fix)
             }
+            Error::VerificationFailed(s) => write!(f, "Verification failed: {}", s),
         }
     }
 }
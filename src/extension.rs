@@ -1,75 +1,135 @@
 use crate::{
+    archive::{download, download_verified, find_executable, ArchiveFormat},
+    cleanup::{remove_stale_installs, remove_stale_toolchains, DEFAULT_KEEP_VERSIONS},
+    debug, diagnostics,
     error::{Error, Result},
+    label,
+    manifest::{self, InstallRecord, Manifest},
+    platform::Platform,
     provider::{
-        fetch_version, AssetInfo, Custom, LspProvider, VersionInfo, Zigscient, Zls, ZlsStable,
+        fetch_version, AssetInfo, Compatibility, Custom, InitializationOptions, LspProvider,
+        VersionInfo, Zigscient, Zls, ZlsStable,
     },
-    settings::{Provider, ZigTooling},
+    settings::{self, Provider, VersionSource, ZigTooling},
+    toolchain::{self, Toolchain},
+    util::{compare_versions, expand_vars, parse_zon, resolve_path, version_from_path, JsonExt},
+    verify::{fetch_signature, Integrity},
+    zigdoc,
 };
-use std::{fs, path::PathBuf};
-use zed_extension_api::{self as zed, serde_json, settings::LspSettings, LanguageServerId};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use zed_extension_api::{self as zed, process, serde_json, settings::LspSettings, LanguageServerId};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ZigExtension {
-    cached_binary_path: Option<PathBuf>,
+    /// Language server binary each server and worktree (by id) was started with
+    binaries_in_use: HashMap<(String, u64), PathBuf>,
+    /// Zig compiler each worktree (by id) was given
+    toolchains_in_use: HashMap<u64, Toolchain>,
     current_settings: ZigTooling,
 }
 
-impl Default for ZigExtension {
-    fn default() -> Self {
-        Self { cached_binary_path: None, current_settings: ZigTooling::default() }
-    }
-}
-
 impl ZigExtension {
-    fn get_provider(&self) -> Box<dyn LspProvider> {
+    fn get_provider(
+        &self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Box<dyn LspProvider> {
         match self.current_settings.provider {
-            Provider::Zls => Box::new(Zls),
+            Provider::Zls => {
+                // A version detected from the compiler in use has to be matched exactly, a
+                // pinned minimum only needs a ZLS that understands it.
+                let (zig_version, compatibility) = match self.zig_version(worktree) {
+                    Some(version) if version != "system" => {
+                        (Some(version), Compatibility::OnlyRuntime)
+                    }
+                    _ => match installed_zig_version(worktree) {
+                        Some(version) => (Some(version), Compatibility::Full),
+                        None => (None, Compatibility::OnlyRuntime),
+                    },
+                };
+                Box::new(Zls { zig_version, compatibility })
+            }
             Provider::Zigscient => Box::new(Zigscient),
-            Provider::Custom => Box::new(Custom),
+            Provider::Custom => Box::new(Custom {
+                settings: self.current_settings.custom.clone(),
+                name: format!("custom-{language_server_id}"),
+            }),
             Provider::ZlsStable => Box::new(ZlsStable),
         }
     }
 
+    /// Zig version the worktree is pinned to
+    ///
+    /// The `zig_version` setting takes precedence over `.minimum_zig_version` in
+    /// `build.zig.zon`.
+    fn zig_version(&self, worktree: &zed::Worktree) -> Option<String> {
+        if let Some(version) = &self.current_settings.zig_version {
+            return Some(version.clone());
+        }
+
+        let manifest = parse_zon(&worktree.read_text_file("build.zig.zon").ok()?).ok()?;
+        manifest.get_str("minimum_zig_version").ok().map(str::to_string)
+    }
+
+    /// Platform to download builds for, the `platform` setting overrides what Zed reports
+    fn platform(&self, worktree: &zed::Worktree) -> Platform {
+        self.current_settings.platform.unwrap_or_else(|| Platform::detect(worktree))
+    }
+
+    /// Identifies what the settings ask for, installs are only reused for the same request
+    fn channel(&self, worktree: &zed::Worktree) -> String {
+        let settings = &self.current_settings;
+        let mut channel = format!(
+            "{}@{}",
+            settings.provider.as_str(),
+            settings.version.as_deref().unwrap_or("latest")
+        );
+
+        match (settings.provider, &settings.version_source) {
+            (Provider::Zls, _) if settings.version.is_none() => {
+                let zig_version = self
+                    .zig_version(worktree)
+                    .filter(|version| version != "system")
+                    .or_else(|| installed_zig_version(worktree));
+                channel.push_str(&format!("/zig@{}", zig_version.as_deref().unwrap_or("master")));
+            }
+            (Provider::Custom, Some(VersionSource::GitHub { url, .. }))
+            | (Provider::Custom, Some(VersionSource::ApiEndpoint { url })) => {
+                channel.push_str(&format!("/{url}"));
+            }
+            _ => {}
+        }
+
+        channel
+    }
+
     fn find_existing_binary(
         &self,
         binary_name: &str,
+        channel: &str,
         worktree: &zed::Worktree,
     ) -> Option<ZigTooling> {
         let settings = &self.current_settings;
 
-        // Check configured path first
-        if let Some(path) = &settings.path {
-            let path = PathBuf::from(path);
-            if path.is_file() {
-                return Some(ZigTooling {
-                    provider: settings.provider,
-                    path: Some(path.to_string_lossy().into()),
-                    args: settings.args.clone(),
-                    version_source: settings.version_source.clone(),
-                });
-            }
+        // A configured path is used as given, the sandbox can't look outside the extension
+        // directory to check it.
+        if settings.path.is_some() {
+            return Some(settings.clone());
         }
 
         // Check PATH
         if let Some(path) = worktree.which(binary_name) {
-            return Some(ZigTooling {
-                provider: settings.provider,
-                path: Some(path),
-                args: settings.args.clone(),
-                version_source: settings.version_source.clone(),
-            });
+            return Some(ZigTooling { path: Some(path), ..settings.clone() });
         }
 
-        // Check cached path
-        if let Some(path) = &self.cached_binary_path {
-            if path.is_file() {
-                return Some(ZigTooling {
-                    provider: settings.provider,
-                    path: Some(path.to_string_lossy().into()),
-                    args: settings.args.clone(),
-                    version_source: settings.version_source.clone(),
-                });
-            }
+        // Check previous installs that don't need an update check yet
+        let policy = settings.update_policy;
+        if let Some(record) = Manifest::load().find(channel).filter(|r| r.is_fresh(policy)) {
+            return Some(ZigTooling { path: Some(record.binary_path.clone()), ..settings.clone() });
         }
 
         None
@@ -77,27 +137,13 @@ impl ZigExtension {
 
     fn download_binary(
         &self,
-        provider: &Box<dyn LspProvider>,
-        platform: zed::Os,
+        provider: &dyn LspProvider,
+        platform: &Platform,
         language_server_id: &LanguageServerId,
-    ) -> Result<PathBuf> {
+        channel: &str,
+    ) -> Result<InstallRecord> {
         let (name, repo) = provider.config();
-
-        let platform_key = format!(
-            "{}-{}",
-            match zed::current_platform().1 {
-                zed::Architecture::Aarch64 => "aarch64",
-                zed::Architecture::X8664 => "x86_64",
-                _ => "x86",
-            },
-            match platform {
-                zed::Os::Mac => "macos",
-                zed::Os::Linux => "linux-gnu",
-                zed::Os::Windows => "windows",
-            }
-        );
-
-        let binary_name = provider.binary_name(name, &platform_key);
+        let platform_key = provider.platform_key(platform);
 
         zed::set_language_server_installation_status(
             language_server_id,
@@ -111,38 +157,30 @@ impl ZigExtension {
                         message: "Custom provider requires version_source".to_string(),
                         fix: "Please specify either a 'github' or 'api' version source in your configuration".to_string(),
                     })?;
-                fetch_version(version_source, &platform_key)?
-            }
-            _ => {
-                let release = zed::latest_github_release(
-                    repo,
-                    zed::GithubReleaseOptions { require_assets: true, pre_release: false },
-                )
-                .map_err(|e| {
-                    Error::LanguageServer(format!("Failed to fetch release from {repo}: {e}"))
-                })?;
-
-                VersionInfo {
-                    version: release.version,
-                    download_url: release
-                        .assets
-                        .iter()
-                        .find(|a| a.name.contains(&platform_key))
-                        .ok_or_else(|| {
-                            Error::AssetNotFound(format!("No asset found for {platform_key}"))
-                        })?
-                        .download_url
-                        .clone(),
-                }
+                fetch_version(
+                    version_source,
+                    &platform_key,
+                    self.current_settings.version.as_deref(),
+                    provider.asset_pattern(platform).as_deref(),
+                )?
             }
+            // zls master builds never make it to GitHub, the provider resolves them itself.
+            Provider::Zls => VersionInfo {
+                version: self.current_settings.version.clone().unwrap_or_default(),
+                download_url: String::new(),
+                shasum: None,
+                size: None,
+            },
+            _ => fetch_version(
+                &VersionSource::GitHub { url: repo.to_string(), pre_release: false },
+                &platform_key,
+                self.current_settings.version.as_deref(),
+                provider.asset_pattern(platform).as_deref(),
+            )?,
         };
 
-        let AssetInfo { name: asset_name, url, .. } = provider.asset_info(
-            name,
-            platform,
-            zed::current_platform().1,
-            &version_info.version,
-        )?;
+        let AssetInfo { name: asset_name, url, shasum, size } =
+            provider.asset_info(name, platform, &version_info.version)?;
 
         let download_url = url.unwrap_or(version_info.download_url);
         if download_url.is_empty() {
@@ -150,117 +188,473 @@ impl ZigExtension {
         }
 
         let version_dir = PathBuf::from(&asset_name);
-        let binary_path = version_dir.join(format!(
-            "{binary_name}{}",
-            if platform == zed::Os::Windows { ".exe" } else { "" }
-        ));
-
-        if !fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
-            zed::set_language_server_installation_status(
-                language_server_id,
-                &zed::LanguageServerInstallationStatus::Downloading,
-            );
+        let file_name = binary_file_name(provider, platform);
+        let mut checksum = shasum.or(version_info.shasum.clone());
+        let mut installed_at = None;
+
+        let binary_path = match find_executable(&version_dir, &file_name) {
+            Some(binary_path) => {
+                installed_at = Manifest::load()
+                    .installs
+                    .iter()
+                    .find(|record| Path::new(&record.binary_path) == binary_path)
+                    .map(|record| record.installed_at);
+                binary_path
+            }
+            None => {
+                zed::set_language_server_installation_status(
+                    language_server_id,
+                    &zed::LanguageServerInstallationStatus::Downloading,
+                );
+
+                fs::create_dir_all(&version_dir).map_err(|e| {
+                    Error::InstallationFailed(format!(
+                        "Failed to create directory {}: {e}",
+                        version_dir.display()
+                    ))
+                })?;
 
-            fs::create_dir_all(&version_dir).map_err(|e| {
-                Error::InstallationFailed(format!(
-                    "Failed to create directory {}: {e}",
-                    version_dir.display()
-                ))
-            })?;
-
-            zed::download_file(
-                &download_url,
-                version_dir
-                    .to_str()
-                    .ok_or_else(|| Error::InstallationFailed("Invalid path".into()))?,
-                if platform == zed::Os::Windows {
-                    zed::DownloadedFileType::Zip
+                let format = ArchiveFormat::detect(&download_url, provider.archive_format())?;
+                if let Some(public_key) = provider.public_key() {
+                    let integrity = Integrity {
+                        shasum: checksum.clone(),
+                        size: size.or(version_info.size),
+                        signature: Some(fetch_signature(&download_url)?),
+                        public_key: Some(public_key),
+                    };
+                    checksum = Some(download_verified(
+                        &download_url,
+                        &version_dir,
+                        format,
+                        &file_name,
+                        &integrity,
+                    )?);
                 } else {
-                    zed::DownloadedFileType::GzipTar
-                },
-            )
-            .map_err(|e| {
-                Error::DownloadFailed(format!("Failed to download from {download_url}: {e}"))
-            })?;
-
-            zed::make_file_executable(&binary_path.to_string_lossy())?;
-
-            // TODO(viable): For now leave everything in place, so it can be reused across different workspaces.
-            // cleanup
-            // let entries =
-            //     fs::read_dir(".").map_err(|e| format!("Failed to list working directory {e}"))?;
-            // for entry in entries {
-            //     let entry = entry.map_err(|e| format!("Failed to load directory entry {e}"))?;
-            //     if entry.file_name().to_str() != Some(version_dir.to_str().unwrap()) {
-            //         fs::remove_dir_all(entry.path()).ok();
-            //     }
-            // }
+                    download(&download_url, &version_dir, format, &file_name)?;
+                }
+
+                let binary_path = find_executable(&version_dir, &file_name).ok_or_else(|| {
+                    Error::InstallationFailed(format!(
+                        "No {file_name} found in the archive downloaded from {download_url}"
+                    ))
+                })?;
+                zed::make_file_executable(&binary_path.to_string_lossy())?;
+
+                // Keep whatever other worktrees are still running, they may be pinned to older
+                // versions.
+                let in_use = self
+                    .binaries_in_use
+                    .values()
+                    .map(PathBuf::as_path)
+                    .chain([binary_path.as_path()])
+                    .collect::<Vec<_>>();
+                remove_stale_installs(
+                    name,
+                    self.current_settings.keep_versions.unwrap_or(DEFAULT_KEEP_VERSIONS),
+                    &in_use,
+                );
+                binary_path
+            }
+        };
+
+        let now = manifest::now();
+        Ok(InstallRecord {
+            provider: self.current_settings.provider.as_str().to_string(),
+            channel: channel.to_string(),
+            version: asset_name.strip_prefix(&format!("{name}-")).unwrap_or(&asset_name).into(),
+            source_url: download_url,
+            checksum,
+            installed_at: installed_at.unwrap_or(now),
+            checked_at: now,
+            binary_path: binary_path.to_string_lossy().into(),
+        })
+    }
+
+    /// Makes sure a Zig compiler is available for the language server
+    ///
+    /// A `zig` on PATH always wins, otherwise the configured version is installed into the
+    /// extension directory.
+    fn ensure_toolchain(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<Toolchain>> {
+        if !self.current_settings.install_zig.unwrap_or(true) || worktree.which("zig").is_some() {
+            return Ok(None);
         }
 
-        Ok(binary_path)
+        let toolchain = if self.current_settings.offline {
+            toolchain::find_installed()
+        } else {
+            self.install_toolchain(language_server_id, worktree)?
+        };
+
+        if let Some(toolchain) = &toolchain {
+            self.toolchains_in_use.insert(worktree.id(), toolchain.clone());
+        }
+        Ok(toolchain)
     }
 
-    fn language_server_binary(
+    fn install_toolchain(
+        &self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<Toolchain>> {
+        let pinned = self.zig_version(worktree);
+        let version = match (&pinned, self.current_settings.provider) {
+            (Some(version), _) if version != "system" => version.as_str(),
+            (_, Provider::ZlsStable) => "stable",
+            _ => "master",
+        };
+
+        let channel = format!("zig@{version}");
+        let mut manifest = Manifest::load();
+        if let Some(record) = manifest
+            .find(&channel)
+            .filter(|record| record.is_fresh(self.current_settings.update_policy))
+        {
+            return Ok(Toolchain::from_record(record));
+        }
+
+        let platform = self.platform(worktree);
+        let installed = toolchain::install(version, &channel, &platform, Some(language_server_id));
+        let toolchain = match installed {
+            Ok(record) => {
+                let toolchain = Toolchain::from_record(&record);
+                // Nightlies are a few hundred MB each, keep them like language servers.
+                let in_use = self
+                    .toolchains_in_use
+                    .values()
+                    .map(|toolchain| toolchain.root.as_path())
+                    .chain(toolchain.as_ref().map(|toolchain| toolchain.root.as_path()))
+                    .collect::<Vec<_>>();
+                remove_stale_toolchains(
+                    self.current_settings.keep_versions.unwrap_or(DEFAULT_KEEP_VERSIONS),
+                    &in_use,
+                );
+
+                manifest.insert(record);
+                manifest.prune();
+                manifest.save()?;
+                toolchain
+            }
+            Err(e) => Some(toolchain::find_installed().ok_or(e)?),
+        };
+        Ok(toolchain)
+    }
+
+    /// Zig whose std `/zigdoc` reads, downloaded on first use
+    ///
+    /// Extensions can't read outside of their own directory, so instead of a zig from PATH the
+    /// same version is installed into the extension directory.
+    fn zigdoc_toolchain(&self, worktree: Option<&zed::Worktree>) -> Result<Toolchain> {
+        if let Some(toolchain) = toolchain::find_installed() {
+            return Ok(toolchain);
+        }
+
+        let version = worktree
+            .and_then(|worktree| {
+                installed_zig_version(worktree).or_else(|| self.zig_version(worktree))
+            })
+            .filter(|version| version != "system")
+            .unwrap_or_else(|| "stable".into());
+        let platform = match worktree {
+            Some(worktree) => self.platform(worktree),
+            None => self.current_settings.platform.ok_or("Open a project to use /zigdoc")?,
+        };
+
+        let record = toolchain::install(&version, &format!("zig@{version}"), &platform, None)?;
+        let toolchain = Toolchain::from_record(&record)
+            .ok_or_else(|| Error::InstallationFailed("No zig executable was installed".into()))?;
+        let mut manifest = Manifest::load();
+        manifest.insert(record);
+        manifest.save()?;
+        Ok(toolchain)
+    }
+
+    /// Whether a language server registered in `extension.toml` should run
+    ///
+    /// Zed starts every registered server by default, so anything but zls only runs once it has
+    /// its own `lsp.<id>` section.
+    fn is_enabled(language_server_id: &LanguageServerId, worktree: &zed::Worktree) -> bool {
+        if language_server_id.as_ref() == "zls" {
+            return true;
+        }
+        LspSettings::for_worktree(language_server_id.as_ref(), worktree).is_ok_and(|settings| {
+            settings.settings.is_some() ||
+                settings.binary.is_some() ||
+                settings.initialization_options.is_some()
+        })
+    }
+
+    /// Reads the settings of one of the language servers registered in `extension.toml`
+    ///
+    /// Every server has its own `lsp.<id>` section, so they are reloaded whenever Zed asks about
+    /// a different one.
+    fn load_settings(
         &mut self,
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
-    ) -> Result<ZigTooling> {
+    ) -> Result<()> {
+        self.current_settings = ZigTooling {
+            provider: Provider::for_language_server(language_server_id.as_ref()),
+            ..Default::default()
+        };
+
         if let Ok(lsp_settings) = LspSettings::for_worktree(language_server_id.as_ref(), worktree) {
-            let mut config = ZigTooling {
-                path: lsp_settings.binary.as_ref().and_then(|b| b.path.clone()),
-                args: lsp_settings.binary.as_ref().and_then(|b| b.arguments.clone()),
-                ..Default::default()
+            let mut config = match lsp_settings.settings {
+                Some(settings) => ZigTooling::from_settings(language_server_id.as_ref(), settings)?,
+                None => self.current_settings.clone(),
             };
+            config.path = lsp_settings.binary.as_ref().and_then(|b| b.path.clone());
+            config.args = lsp_settings.binary.as_ref().and_then(|b| b.arguments.clone());
+            expand_settings(&mut config, worktree);
 
-            if let Some(settings) = lsp_settings.settings {
-                if let Some(Ok(provider)) =
-                    settings.get("provider").map(|v| serde_json::from_value(v.clone()))
-                {
-                    config.provider = provider;
-                }
+            self.current_settings = config;
+        }
 
-                if let Some(Ok(version_source)) =
-                    settings.get("version_source").map(|v| serde_json::from_value(v.clone()))
-                {
-                    config.version_source = Some(version_source);
-                }
+        match (self.current_settings.provider, &self.current_settings.version_source) {
+            (Provider::Custom, None) => Err(Error::Configuration {
+                message: format!(
+                    "{language_server_id} uses the custom provider, which requires version_source \
+                     configuration"
+                ),
+                fix: format!(
+                    "Please add a version_source configuration with either 'github' or 'api' \
+                     type, or disable it with \"!{language_server_id}\" in the Zig \
+                     language_servers setting, e.g.\n{}",
+                    diagnostics::example(
+                        language_server_id.as_ref(),
+                        "version_source",
+                        settings::example_value("version_source"),
+                    )
+                ),
             }
+            .into()),
+            (Provider::Custom, Some(vs)) => vs.validate(),
+            _ => Ok(()),
+        }
+    }
 
-            self.current_settings = match (config.provider, &config.version_source) {
-                (Provider::Custom, None) => {
-                    return Err(Error::Configuration {
-                        message: "Custom provider requires version_source configuration".into(),
-                        fix: "Please add a version_source configuration with either 'github' or 'api' type".into(),
-                    }.into())
-                },
-                (Provider::Custom, Some(vs)) => vs.validate().map(|_| config)?,
-                _ => config,
-            };
+    /// Installs the Zig compiler the server is started with, if one is needed
+    ///
+    /// The server still works without zig, it just can't run builds or `ast-check`, so a failed
+    /// install only shows up as a status.
+    fn prepare_toolchain(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) {
+        if let Err(e) = self.ensure_toolchain(language_server_id, worktree) {
+            zed::set_language_server_installation_status(
+                language_server_id,
+                &zed::LanguageServerInstallationStatus::Failed(format!(
+                    "Zig could not be installed, starting without it: {e}"
+                )),
+            );
+        }
+    }
+
+    /// Options for the server in its own dialect, sent both on startup and whenever it asks for
+    /// its configuration
+    ///
+    /// Only merges in what the server was started with, nothing is installed or probed here as
+    /// servers ask for their configuration all the time.
+    fn server_options(
+        &self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+        options: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let mut options = InitializationOptions::from_value(options)?;
+
+        // The servers are started outside of the extension sandbox, so they need absolute paths.
+        let root = std::env::current_dir().map_err(Error::from)?;
+        if let Some(toolchain) = self.toolchains_in_use.get(&worktree.id()) {
+            options.zig_exe_path.get_or_insert_with(|| {
+                root.join(toolchain.zig_exe_path()).to_string_lossy().into_owned()
+            });
+            options.zig_lib_path.get_or_insert_with(|| {
+                root.join(toolchain.zig_lib_path()).to_string_lossy().into_owned()
+            });
+        }
+        if options.build_runner_path.is_none() {
+            options.build_runner_path = self
+                .binaries_in_use
+                .get(&(language_server_id.to_string(), worktree.id()))
+                .and_then(|binary| bundled_build_runner(binary))
+                .map(|path| root.join(path).to_string_lossy().into_owned());
         }
 
-        let provider = self.get_provider();
+        // Only the dialect matters here, resolving the zls build would run `zig version`.
+        match self.current_settings.provider {
+            Provider::Zls => Zls::default().initialization_options(options),
+            _ => self.get_provider(language_server_id, worktree).initialization_options(options),
+        }
+    }
+
+    fn language_server_binary(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<ZigTooling> {
+        self.load_settings(language_server_id, worktree)?;
+
+        let provider = self.get_provider(language_server_id, worktree);
         let (name, _) = provider.config();
+        let channel = self.channel(worktree);
+        let path_name = match self.current_settings.provider {
+            Provider::Custom => {
+                self.current_settings.custom.binary_name.as_deref().unwrap_or("custom")
+            }
+            _ => name,
+        };
 
-        let tooling = if let Some(binary) = self.find_existing_binary(name, worktree) {
+        let tooling = if let Some(binary) = self.find_existing_binary(path_name, &channel, worktree)
+        {
             binary
         } else {
-            let platform = zed::current_platform().0;
-            let binary_path = self.download_binary(&provider, platform, language_server_id)?;
-
-            self.cached_binary_path = Some(binary_path.clone());
+            let platform = self.platform(worktree);
+            let pinned = self.current_settings.version.as_deref();
+            let binary_path = if self.current_settings.offline {
+                find_installed_binary(provider.as_ref(), &platform, pinned).ok_or_else(|| {
+                    Error::Configuration {
+                        message: format!("No {name} installation found while offline"),
+                        fix: "Disable \"offline\" once so the language server can be downloaded"
+                            .into(),
+                    }
+                })?
+            } else {
+                let record = self.download_binary(
+                    provider.as_ref(),
+                    &platform,
+                    language_server_id,
+                    &channel,
+                );
+                match record {
+                    Ok(record) => {
+                        let path = PathBuf::from(&record.binary_path);
+                        let mut manifest = Manifest::load();
+                        manifest.insert(record);
+                        manifest.prune();
+                        manifest.save()?;
+                        path
+                    }
+                    Err(e) => {
+                        let path = find_installed_binary(provider.as_ref(), &platform, pinned)
+                            .ok_or_else(|| e.clone())?;
+                        zed::set_language_server_installation_status(
+                            language_server_id,
+                            &zed::LanguageServerInstallationStatus::Failed(format!(
+                                "Update failed, falling back to {}: {e}",
+                                path.display()
+                            )),
+                        );
+                        path
+                    }
+                }
+            };
 
             ZigTooling {
-                provider: self.current_settings.provider,
                 path: Some(binary_path.to_string_lossy().into()),
-                args: self.current_settings.args.clone(),
-                version_source: self.current_settings.version_source.clone(),
+                ..self.current_settings.clone()
             }
         };
 
+        if let Some(path) = &tooling.path {
+            self.binaries_in_use
+                .insert((language_server_id.to_string(), worktree.id()), PathBuf::from(path));
+        }
+
         Ok(tooling)
     }
 }
 
+fn binary_file_name(provider: &dyn LspProvider, platform: &Platform) -> String {
+    let binary_name = provider.binary_name(provider.config().0, platform);
+    if platform.is_windows() && !binary_name.ends_with(".exe") {
+        format!("{binary_name}.exe")
+    } else {
+        binary_name
+    }
+}
+
+/// Newest install of the provider's language server left in the extension directory
+///
+/// Install directories are named `<name>-<version>` by `download_binary`. A pinned version only
+/// matches its own install.
+fn find_installed_binary(
+    provider: &dyn LspProvider,
+    platform: &Platform,
+    pinned: Option<&str>,
+) -> Option<PathBuf> {
+    let prefix = format!("{}-", provider.config().0);
+    let file_name = binary_file_name(provider, platform);
+
+    fs::read_dir(".")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let dir = entry.file_name().into_string().ok()?;
+            let version = dir.strip_prefix(&prefix)?.to_string();
+            if pinned.is_some_and(|pinned| pinned != version) {
+                return None;
+            }
+            let path = find_executable(Path::new(&dir), &file_name)?;
+            Some((version, path))
+        })
+        .max_by(|(a, _), (b, _)| compare_versions(a, b))
+        .map(|(_, path)| path)
+}
+
+/// `build_runner.zig` shipped next to a language server installed into the extension directory
+///
+/// Releases normally embed the build runner, only some forks ship it as a separate file.
+fn bundled_build_runner(binary_path: &Path) -> Option<PathBuf> {
+    let Some(Component::Normal(install_dir)) = binary_path.components().next() else {
+        return None;
+    };
+    find_executable(Path::new(install_dir), "build_runner.zig")
+}
+
+/// Expands variables in the configured binary and release source, so checked in project
+/// settings work on every machine
+fn expand_settings(settings: &mut ZigTooling, worktree: &zed::Worktree) {
+    let env = worktree.shell_env();
+    let root = worktree.root_path();
+    let expand = |text: &str| expand_vars(text, &env, &root);
+
+    if let Some(path) = &mut settings.path {
+        *path = resolve_path(&expand(path), &root);
+    }
+    for arg in settings.args.iter_mut().flatten() {
+        *arg = expand(arg);
+    }
+    if let Some(VersionSource::GitHub { url, .. } | VersionSource::ApiEndpoint { url }) =
+        &mut settings.version_source
+    {
+        *url = expand(url);
+    }
+}
+
+/// Version of the `zig` found in the worktree's PATH
+///
+/// Asks `zig version`. When it can't be run, version managers and release tarballs putting the
+/// version into the install path (e.g. `~/.zvm/0.13.0/zig`, `zig-linux-x86_64-0.14.0/zig`) are
+/// the fallback.
+fn installed_zig_version(worktree: &zed::Worktree) -> Option<String> {
+    let path = worktree.which("zig")?;
+    let output = process::Command::new("zig").arg("version").envs(worktree.shell_env()).output();
+
+    output
+        .ok()
+        .filter(|output| output.status == Some(0))
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        .or_else(|| version_from_path(&path))
+}
+
 impl zed::Extension for ZigExtension {
     fn new() -> Self {
         Self::default()
@@ -271,6 +665,13 @@ impl zed::Extension for ZigExtension {
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<zed::Command> {
+        if !Self::is_enabled(language_server_id, worktree) {
+            return Err(format!(
+                "{language_server_id} is opt-in. To start it next to zls, add an \
+                 lsp.{language_server_id} section to your settings. To hide this message, add \
+                 \"!{language_server_id}\" to the Zig language_servers setting."
+            ));
+        }
         let binary = self.language_server_binary(language_server_id, worktree)?;
 
         let environment = match zed::current_platform().0 {
@@ -284,4 +685,128 @@ impl zed::Extension for ZigExtension {
             env: environment.unwrap_or_default(),
         })
     }
+
+    fn language_server_initialization_options(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<serde_json::Value>> {
+        if !Self::is_enabled(language_server_id, worktree) {
+            return Ok(None);
+        }
+        let options = LspSettings::for_worktree(language_server_id.as_ref(), worktree)
+            .ok()
+            .and_then(|settings| settings.initialization_options)
+            .unwrap_or_else(|| serde_json::json!({}));
+        self.load_settings(language_server_id, worktree)?;
+        self.prepare_toolchain(language_server_id, worktree);
+        Ok(Some(self.server_options(language_server_id, worktree, options)?))
+    }
+
+    fn label_for_completion(
+        &self,
+        _language_server_id: &LanguageServerId,
+        completion: zed::lsp::Completion,
+    ) -> Option<zed::CodeLabel> {
+        label::completion(&completion)
+    }
+
+    fn label_for_symbol(
+        &self,
+        _language_server_id: &LanguageServerId,
+        symbol: zed::lsp::Symbol,
+    ) -> Option<zed::CodeLabel> {
+        label::symbol(&symbol)
+    }
+
+    fn complete_slash_command_argument(
+        &self,
+        command: zed::SlashCommand,
+        args: Vec<String>,
+    ) -> Result<Vec<zed::SlashCommandArgumentCompletion>> {
+        match command.name.as_str() {
+            "zigdoc" => zigdoc::complete(&args),
+            command => Err(format!("Unknown slash command: \"{command}\"")),
+        }
+    }
+
+    fn run_slash_command(
+        &self,
+        command: zed::SlashCommand,
+        args: Vec<String>,
+        worktree: Option<&zed::Worktree>,
+    ) -> Result<zed::SlashCommandOutput> {
+        match command.name.as_str() {
+            "zigdoc" => zigdoc::run(&args, &self.zigdoc_toolchain(worktree)?.zig_lib_path()),
+            command => Err(format!("Unknown slash command: \"{command}\"")),
+        }
+    }
+
+    fn language_server_workspace_configuration(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<serde_json::Value>> {
+        if !Self::is_enabled(language_server_id, worktree) {
+            return Ok(None);
+        }
+        self.load_settings(language_server_id, worktree)?;
+        let mut options = LspSettings::for_worktree(language_server_id.as_ref(), worktree)
+            .ok()
+            .and_then(|settings| settings.initialization_options)
+            .unwrap_or_else(|| serde_json::json!({}));
+
+        // Options from `settings.zls` win over the ones sent on startup, so they can be changed
+        // without restarting the server.
+        if let Some(options) = options.as_object_mut() {
+            options.extend(self.current_settings.zls.clone());
+        }
+
+        let options = self.server_options(language_server_id, worktree, options)?;
+        Ok(Some(serde_json::json!({ "zls": options })))
+    }
+
+    fn get_dap_binary(
+        &mut self,
+        adapter_name: String,
+        config: zed::DebugTaskDefinition,
+        user_provided_debug_adapter_path: Option<String>,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::DebugAdapterBinary> {
+        if adapter_name != debug::ADAPTER_NAME {
+            return Err(format!("Unknown debug adapter: \"{adapter_name}\""));
+        }
+        debug::adapter_binary(config, user_provided_debug_adapter_path, worktree)
+    }
+
+    fn dap_request_kind(
+        &mut self,
+        _adapter_name: String,
+        config: serde_json::Value,
+    ) -> Result<zed::StartDebuggingRequestArgumentsRequest> {
+        debug::request_kind(&config)
+    }
+
+    fn dap_config_to_scenario(&mut self, config: zed::DebugConfig) -> Result<zed::DebugScenario> {
+        debug::config_to_scenario(config)
+    }
+
+    fn dap_locator_create_scenario(
+        &mut self,
+        locator_name: String,
+        build_task: zed::TaskTemplate,
+        resolved_label: String,
+        debug_adapter_name: String,
+    ) -> Option<zed::DebugScenario> {
+        (locator_name == debug::LOCATOR_NAME)
+            .then(|| debug::create_scenario(build_task, resolved_label, debug_adapter_name))?
+    }
+
+    fn run_dap_locator(
+        &mut self,
+        _locator_name: String,
+        build_task: zed::TaskTemplate,
+    ) -> Result<zed::DebugRequest> {
+        debug::run_locator(build_task)
+    }
 }
@@ -0,0 +1,250 @@
+use zed_extension_api::{
+    lsp::{Completion, CompletionKind, Symbol, SymbolKind},
+    CodeLabel, CodeLabelSpan,
+};
+
+/// Highlighted label for a ZLS completion item
+///
+/// The shown text is embedded into a bit of valid Zig so the grammar can highlight it, e.g. a
+/// field `len: usize` is parsed as `const _ = struct { len: usize };`.
+pub fn completion(completion: &Completion) -> Option<CodeLabel> {
+    let name = completion.label.as_str();
+    let detail = completion.detail.as_deref().map(str::trim).filter(|detail| !detail.is_empty());
+
+    match completion.kind? {
+        CompletionKind::Function | CompletionKind::Method => {
+            let text = match (detail, &completion.label_details) {
+                // ZLS puts the whole signature into the detail.
+                (Some(signature), _) if signature.starts_with("fn ") => signature.to_string(),
+                (_, Some(details)) => format!(
+                    "fn {name}{}{}",
+                    details.detail.as_deref().unwrap_or("()"),
+                    details.description.as_deref().map(|ty| format!(" {ty}")).unwrap_or_default()
+                ),
+                _ => return None,
+            };
+            Some(code_label("", &text, " {}", name))
+        }
+        CompletionKind::Field => match detail {
+            Some(ty) => {
+                Some(code_label("const _ = struct { ", &format!("{name}: {ty}"), " };", name))
+            }
+            None => Some(code_label("const _ = struct { ", name, ": u0 };", name)),
+        },
+        CompletionKind::EnumMember => Some(code_label("const _ = enum { ", name, " };", name)),
+        CompletionKind::Constant | CompletionKind::Variable
+            if detail.is_some_and(|detail| detail.starts_with("error.")) =>
+        {
+            Some(code_label("const _ = ", &format!("error.{name}"), ";", name))
+        }
+        kind @ (CompletionKind::Constant | CompletionKind::Variable) => {
+            let keyword = if matches!(kind, CompletionKind::Variable) { "var" } else { "const" };
+            let text = match detail {
+                Some(ty) => format!("{keyword} {name}: {ty}"),
+                None => format!("{keyword} {name}"),
+            };
+            Some(code_label("", &text, " = undefined;", name))
+        }
+        CompletionKind::Struct | CompletionKind::Class | CompletionKind::Interface => {
+            Some(code_label("", &format!("const {name} = struct"), " {};", name))
+        }
+        CompletionKind::Enum => Some(code_label("", &format!("const {name} = enum"), " {};", name)),
+        _ => None,
+    }
+}
+
+/// Highlighted label for a document or workspace symbol, prefixed with its kind keyword
+///
+/// Only the kinds ZLS reports are handled. Symbols don't carry a type, so error sets are told
+/// apart by Zig's naming convention (`Error`, `ParseError`).
+pub fn symbol(symbol: &Symbol) -> Option<CodeLabel> {
+    let name = symbol.name.as_str();
+
+    // Tests are reported with their description as the name, with or without the keyword.
+    if let Some(description) = name.strip_prefix("test ").or(name.starts_with('"').then_some(name))
+    {
+        let text = format!("test {description}");
+        return Some(code_label("", &text, " {}", description.trim_matches('"')));
+    }
+
+    match symbol.kind {
+        SymbolKind::Function | SymbolKind::Method => {
+            Some(code_label("", &format!("fn {name}"), "() void {}", name))
+        }
+        SymbolKind::Constant | SymbolKind::Enum if is_error_set(name) => {
+            Some(code_label("", &format!("const {name} = error"), " {};", name))
+        }
+        SymbolKind::Struct => Some(code_label("", &format!("const {name} = struct"), " {};", name)),
+        SymbolKind::Enum => Some(code_label("", &format!("const {name} = enum"), " {};", name)),
+        SymbolKind::Field => Some(code_label("const _ = struct { ", name, ": u0 };", name)),
+        SymbolKind::EnumMember => Some(code_label("const _ = enum { ", name, " };", name)),
+        SymbolKind::Constant => {
+            Some(code_label("", &format!("const {name}"), " = undefined;", name))
+        }
+        SymbolKind::Variable => Some(code_label("", &format!("var {name}"), " = undefined;", name)),
+        _ => None,
+    }
+}
+
+/// Whether a type name follows the naming convention of error sets
+fn is_error_set(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && name.ends_with("Error")
+}
+
+/// Label showing `text`, which is parsed as `prefix + text + suffix`
+///
+/// Only `name` is used for filtering, so typing keywords or types doesn't match everything.
+fn code_label(prefix: &str, text: &str, suffix: &str, name: &str) -> CodeLabel {
+    let start = prefix.len();
+    let filter_range = match find_name(text, name) {
+        Some(filter_start) => filter_start .. filter_start + name.len(),
+        None => 0 .. text.len(),
+    };
+
+    CodeLabel {
+        code: format!("{prefix}{text}{suffix}"),
+        spans: vec![CodeLabelSpan::code_range(start .. start + text.len())],
+        filter_range: filter_range.into(),
+    }
+}
+
+/// Position of `name` as a whole word in `text`
+fn find_name(text: &str, name: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(name).map(|(start, _)| start).find(|&start| {
+        !text[.. start].ends_with(is_ident) && !text[start + name.len() ..].starts_with(is_ident)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parsed code, the highlighted text and the filtered part of it
+    fn parts(label: CodeLabel) -> (String, String, String) {
+        let [CodeLabelSpan::CodeRange(span)] = label.spans.as_slice() else {
+            panic!("expected a single code range in {:?}", label.spans);
+        };
+        let text = label.code[span.start as usize .. span.end as usize].to_string();
+        let filter =
+            text[label.filter_range.start as usize .. label.filter_range.end as usize].to_string();
+        (label.code, text, filter)
+    }
+
+    fn symbol_parts(kind: SymbolKind, name: &str) -> Option<(String, String, String)> {
+        symbol(&Symbol { kind, name: name.into() }).map(parts)
+    }
+
+    fn completion_parts(
+        kind: CompletionKind,
+        label: &str,
+        detail: Option<&str>,
+    ) -> (String, String, String) {
+        let completion = Completion {
+            label: label.into(),
+            label_details: None,
+            detail: detail.map(str::to_string),
+            kind: Some(kind),
+            insert_text_format: None,
+        };
+        parts(self::completion(&completion).unwrap())
+    }
+
+    #[test]
+    fn labels_symbols_with_their_keyword() {
+        for (kind, name, code, text) in [
+            (SymbolKind::Function, "init", "fn init() void {}", "fn init"),
+            (SymbolKind::Struct, "List", "const List = struct {};", "const List = struct"),
+            (SymbolKind::Enum, "Tag", "const Tag = enum {};", "const Tag = enum"),
+            (SymbolKind::Field, "len", "const _ = struct { len: u0 };", "len"),
+            (SymbolKind::EnumMember, "red", "const _ = enum { red };", "red"),
+            (SymbolKind::Constant, "max", "const max = undefined;", "const max"),
+            (SymbolKind::Variable, "count", "var count = undefined;", "var count"),
+        ] {
+            assert_eq!(
+                symbol_parts(kind, name),
+                Some((code.into(), text.into(), name.into())),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn labels_error_sets() {
+        for (kind, name) in [(SymbolKind::Constant, "ParseError"), (SymbolKind::Enum, "Error")] {
+            let code = format!("const {name} = error {{}};");
+            let text = format!("const {name} = error");
+            assert_eq!(symbol_parts(kind, name), Some((code, text, name.into())));
+        }
+        // Only TitleCase type names count, a value like `lastError` stays a constant.
+        assert_eq!(
+            symbol_parts(SymbolKind::Constant, "lastError").unwrap().0,
+            "const lastError = undefined;"
+        );
+    }
+
+    #[test]
+    fn labels_tests_by_description() {
+        for name in ["\"adds numbers\"", "test \"adds numbers\""] {
+            assert_eq!(
+                symbol_parts(SymbolKind::Method, name),
+                Some((
+                    "test \"adds numbers\" {}".into(),
+                    "test \"adds numbers\"".into(),
+                    "adds numbers".into()
+                )),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            symbol_parts(SymbolKind::Method, "test add"),
+            Some(("test add {}".into(), "test add".into(), "add".into()))
+        );
+    }
+
+    #[test]
+    fn skips_kinds_zls_does_not_report() {
+        for kind in [SymbolKind::Interface, SymbolKind::Class, SymbolKind::File, SymbolKind::Module]
+        {
+            assert_eq!(symbol_parts(kind, "Thing"), None);
+        }
+    }
+
+    #[test]
+    fn labels_completions() {
+        assert_eq!(
+            completion_parts(CompletionKind::Function, "add", Some("fn add(a: u32, b: u32) u32")),
+            (
+                "fn add(a: u32, b: u32) u32 {}".into(),
+                "fn add(a: u32, b: u32) u32".into(),
+                "add".into()
+            )
+        );
+        assert_eq!(
+            completion_parts(CompletionKind::Field, "len", Some("usize")),
+            ("const _ = struct { len: usize };".into(), "len: usize".into(), "len".into())
+        );
+        assert_eq!(
+            completion_parts(CompletionKind::Constant, "OutOfMemory", Some("error.OutOfMemory")),
+            (
+                "const _ = error.OutOfMemory;".into(),
+                "error.OutOfMemory".into(),
+                "OutOfMemory".into()
+            )
+        );
+        assert_eq!(
+            completion_parts(CompletionKind::Variable, "count", Some("u32")),
+            ("var count: u32 = undefined;".into(), "var count: u32".into(), "count".into())
+        );
+    }
+
+    #[test]
+    fn filters_on_the_name_as_a_whole_word() {
+        let (_, text, filter) =
+            completion_parts(CompletionKind::Function, "a", Some("fn a(a: u8) void"));
+        assert_eq!((text.as_str(), filter.as_str()), ("fn a(a: u8) void", "a"));
+        assert_eq!(find_name("fn add(a: u8) void", "a"), Some(7));
+        assert_eq!(find_name("fn add_a(x: u8) void", "a"), None);
+        assert_eq!(find_name("const max_len", "max"), None);
+    }
+}
@@ -1,8 +1,18 @@
+mod archive;
+mod cleanup;
+mod debug;
+mod diagnostics;
 mod error;
 mod extension;
+mod label;
+mod manifest;
+mod platform;
 mod provider;
 mod settings;
+mod toolchain;
 mod util;
+mod verify;
+mod zigdoc;
 
 pub use extension::ZigExtension;
 use zed_extension_api as zed;
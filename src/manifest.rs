@@ -0,0 +1,89 @@
+use crate::{
+    error::{Error, Result},
+    settings::UpdatePolicy,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use zed_extension_api::serde_json;
+
+/// File in the extension directory the manifest is persisted to
+const MANIFEST_PATH: &str = "installs.json";
+
+/// A language server or Zig compiler install made by the extension
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub provider: String,
+    /// What was asked for, e.g. "zls@latest", "zls-stable@0.13.0" or "zig@master"
+    ///
+    /// Installs are only reused for the exact same request.
+    pub channel: String,
+    pub version: String,
+    pub source_url: String,
+    /// SHA-256 of the downloaded archive, if known
+    pub checksum: Option<String>,
+    /// Unix timestamps in seconds
+    pub installed_at: u64,
+    pub checked_at: u64,
+    pub binary_path: String,
+}
+
+impl InstallRecord {
+    /// Whether the install can be used without checking for updates first
+    pub fn is_fresh(&self, policy: UpdatePolicy) -> bool {
+        policy.interval().map_or(true, |interval| now().saturating_sub(self.checked_at) < interval)
+    }
+}
+
+/// Persisted list of installs, survives extension reloads and Zed restarts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub installs: Vec<InstallRecord>,
+}
+
+impl Manifest {
+    /// Reads the manifest, a missing or unreadable one is treated as empty
+    pub fn load() -> Self {
+        fs::read(MANIFEST_PATH)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::SerializationFailed(e.to_string()))?;
+        fs::write(MANIFEST_PATH, data).map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Most recently checked install for `channel` whose binary still exists
+    pub fn find(&self, channel: &str) -> Option<&InstallRecord> {
+        self.installs
+            .iter()
+            .filter(|record| record.channel == channel)
+            .filter(|record| Path::new(&record.binary_path).is_file())
+            .max_by_key(|record| record.checked_at)
+    }
+
+    /// Adds an install, replacing an earlier record of the same binary
+    pub fn insert(&mut self, record: InstallRecord) {
+        self.installs.retain(|existing| {
+            existing.binary_path != record.binary_path || existing.channel != record.channel
+        });
+        self.installs.push(record);
+    }
+
+    /// Drops records whose binaries have been removed from disk
+    pub fn prune(&mut self) {
+        self.installs.retain(|record| Path::new(&record.binary_path).is_file());
+    }
+}
+
+/// Current time as a Unix timestamp in seconds
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
@@ -0,0 +1,231 @@
+use crate::error::Result;
+use serde::Deserialize;
+use std::fmt;
+use zed_extension_api as zed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Os {
+    Macos,
+    Linux,
+    Windows,
+    Freebsd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    X86,
+    X8664,
+    Aarch64,
+    Riscv64,
+}
+
+/// C library Linux binaries are linked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Abi {
+    Gnu,
+    Musl,
+}
+
+/// Platform release assets have to be built for
+///
+/// Zed only reports macOS, Linux and Windows on x86, x86_64 and aarch64, everything else has to
+/// be configured through the `platform` setting (e.g. "riscv64-linux" or "x86_64-freebsd").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Platform {
+    pub os: Os,
+    pub arch: Arch,
+    /// Only set on Linux
+    pub abi: Option<Abi>,
+}
+
+impl Platform {
+    /// Platform Zed runs on
+    ///
+    /// Extensions can't inspect the system libc, a worktree with Alpine's `apk` on its PATH is
+    /// assumed to be musl based.
+    pub fn detect(worktree: &zed::Worktree) -> Self {
+        let (os, arch) = zed::current_platform();
+        let os = match os {
+            zed::Os::Mac => Os::Macos,
+            zed::Os::Linux => Os::Linux,
+            zed::Os::Windows => Os::Windows,
+        };
+        let arch = match arch {
+            zed::Architecture::X86 => Arch::X86,
+            zed::Architecture::X8664 => Arch::X8664,
+            zed::Architecture::Aarch64 => Arch::Aarch64,
+        };
+        let abi = match os {
+            Os::Linux if worktree.which("apk").is_some() => Some(Abi::Musl),
+            Os::Linux => Some(Abi::Gnu),
+            _ => None,
+        };
+        Self { os, arch, abi }
+    }
+
+    pub fn os_name(&self) -> &'static str {
+        match self.os {
+            Os::Macos => "macos",
+            Os::Linux => "linux",
+            Os::Windows => "windows",
+            Os::Freebsd => "freebsd",
+        }
+    }
+
+    pub fn arch_name(&self) -> &'static str {
+        match self.arch {
+            Arch::X86 => "x86",
+            Arch::X8664 => "x86_64",
+            Arch::Aarch64 => "aarch64",
+            Arch::Riscv64 => "riscv64",
+        }
+    }
+
+    /// Key used by the Zig download index and the zigtools release API (e.g. "x86_64-linux")
+    ///
+    /// Zig and ZLS binaries are statically linked, so there is no libc part.
+    pub fn zig_key(&self) -> String {
+        format!("{}-{}", self.arch_name(), self.os_name())
+    }
+
+    /// Full target triple used in GitHub release asset names (e.g. "x86_64-linux-musl")
+    pub fn triple(&self) -> String {
+        match self.abi {
+            Some(Abi::Gnu) => format!("{}-gnu", self.zig_key()),
+            Some(Abi::Musl) => format!("{}-musl", self.zig_key()),
+            None => self.zig_key(),
+        }
+    }
+
+    pub fn is_windows(&self) -> bool {
+        self.os == Os::Windows
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.triple())
+    }
+}
+
+impl TryFrom<String> for Platform {
+    type Error = String;
+
+    /// Parses "<arch>-<os>" with an optional "-gnu" or "-musl" suffix on Linux
+    ///
+    /// Errors only describe the value, they end up in the configuration error of the setting.
+    fn try_from(value: String) -> Result<Self> {
+        let invalid = |message: String| {
+            format!(
+                "{message}, expected \"<arch>-<os>\" or \"<arch>-linux-musl\", e.g. \
+                 \"riscv64-linux\""
+            )
+        };
+
+        let mut parts = value.split('-');
+        let arch = match parts.next().unwrap_or_default() {
+            "x86" => Arch::X86,
+            "x86_64" => Arch::X8664,
+            "aarch64" => Arch::Aarch64,
+            "riscv64" => Arch::Riscv64,
+            arch => return Err(invalid(format!("Unsupported architecture \"{arch}\""))),
+        };
+        let os = match parts.next().unwrap_or_default() {
+            "macos" => Os::Macos,
+            "linux" => Os::Linux,
+            "windows" => Os::Windows,
+            "freebsd" => Os::Freebsd,
+            os => return Err(invalid(format!("Unsupported operating system \"{os}\""))),
+        };
+        let abi = match (os, parts.next()) {
+            (Os::Linux, None | Some("gnu")) => Some(Abi::Gnu),
+            (Os::Linux, Some("musl")) => Some(Abi::Musl),
+            (_, None) => None,
+            (_, Some(abi)) => {
+                return Err(invalid(format!("Unsupported abi \"{abi}\" for {value}")));
+            }
+        };
+        if parts.next().is_some() {
+            return Err(invalid(format!("Invalid platform {value:?}")));
+        }
+
+        Ok(Self { os, arch, abi })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCHES: [(&str, Arch); 4] = [
+        ("x86", Arch::X86),
+        ("x86_64", Arch::X8664),
+        ("aarch64", Arch::Aarch64),
+        ("riscv64", Arch::Riscv64),
+    ];
+    const OSES: [(&str, Os); 4] = [
+        ("macos", Os::Macos),
+        ("linux", Os::Linux),
+        ("windows", Os::Windows),
+        ("freebsd", Os::Freebsd),
+    ];
+
+    fn parse(value: &str) -> Result<Platform> {
+        Platform::try_from(value.to_string())
+    }
+
+    #[test]
+    fn parses_every_combination() {
+        for (arch_name, arch) in ARCHES {
+            for (os_name, os) in OSES {
+                let key = format!("{arch_name}-{os_name}");
+                let abis =
+                    [("", Some(Abi::Gnu)), ("-gnu", Some(Abi::Gnu)), ("-musl", Some(Abi::Musl))];
+
+                for (suffix, linux_abi) in abis {
+                    let value = format!("{key}{suffix}");
+                    match (os, suffix) {
+                        (Os::Linux, _) => {
+                            let platform = parse(&value).unwrap();
+                            assert_eq!(platform, Platform { os, arch, abi: linux_abi });
+                            assert_eq!(platform.zig_key(), key);
+                            let abi = if suffix == "-musl" { "musl" } else { "gnu" };
+                            assert_eq!(platform.triple(), format!("{key}-{abi}"));
+                        }
+                        (_, "") => {
+                            let platform = parse(&value).unwrap();
+                            assert_eq!(platform, Platform { os, arch, abi: None });
+                            assert_eq!(platform.zig_key(), key);
+                            assert_eq!(platform.triple(), key);
+                            assert_eq!(platform.is_windows(), os == Os::Windows);
+                        }
+                        _ => assert!(parse(&value).is_err(), "{value} should be rejected"),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_platforms() {
+        for value in [
+            "",
+            "x86_64",
+            "arm-linux",
+            "x86_64-darwin",
+            "x86_64-linux-android",
+            "x86_64-linux-gnu-extra",
+            "X86_64-linux",
+            "linux-x86_64",
+        ] {
+            assert!(parse(value).is_err(), "{value} should be rejected");
+        }
+    }
+
+    #[test]
+    fn displays_triple() {
+        assert_eq!(parse("aarch64-linux-musl").unwrap().to_string(), "aarch64-linux-musl");
+        assert_eq!(parse("x86_64-windows").unwrap().to_string(), "x86_64-windows");
+    }
+}
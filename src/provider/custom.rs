@@ -1,29 +1,39 @@
 use super::{AssetInfo, LspProvider, Result};
-use zed_extension_api::{Architecture, Os};
+use crate::{archive::ArchiveFormat, platform::Platform, settings::CustomSettings};
 
-pub struct Custom;
+pub struct Custom {
+    pub settings: CustomSettings,
+    /// Install directory prefix, `custom-<language server id>` so servers don't share installs
+    pub name: String,
+}
 
 impl LspProvider for Custom {
-    // FIXME(viable): Be able to add custom names. so its usable.
-    fn config(&self) -> (&'static str, &'static str) {
-        ("custom", "")
+    fn config(&self) -> (&str, &str) {
+        (&self.name, "")
     }
 
-    fn asset_info(
-        &self,
-        name: &str,
-        _platform: Os,
-        _arch: Architecture,
-        version: &str,
-    ) -> Result<AssetInfo> {
+    fn asset_info(&self, name: &str, _platform: &Platform, version: &str) -> Result<AssetInfo> {
 
         Ok(AssetInfo {
             name: format!("{}-{}", name, version),
             url: None,
+            shasum: None,
+            size: None,
         })
     }
 
-    fn binary_name(&self, name: &str, _platform_key: &str) -> String {
-        name.to_string()
+    fn binary_name(&self, _name: &str, _platform: &Platform) -> String {
+        let settings = &self.settings;
+        let binary_name = settings.binary_path.clone().or_else(|| settings.binary_name.clone());
+        binary_name.unwrap_or_else(|| "custom".into())
+    }
+
+    fn asset_pattern(&self, platform: &Platform) -> Option<String> {
+        let pattern = self.settings.asset_pattern.as_ref()?;
+        Some(pattern.replace("{os}", platform.os_name()).replace("{arch}", platform.arch_name()))
+    }
+
+    fn archive_format(&self) -> Option<ArchiveFormat> {
+        self.settings.archive
     }
 }
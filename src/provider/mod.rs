@@ -1,36 +1,66 @@
-use crate::error::Result;
-use zed_extension_api as zed;
+use crate::{archive::ArchiveFormat, error::Result, platform::Platform};
+use options::ZlsOptions;
+use zed_extension_api::serde_json::Value;
 
 mod custom;
+mod options;
 mod version;
 mod zigscient;
 mod zls;
 mod zls_stable;
 
 pub use custom::Custom;
+pub use options::InitializationOptions;
 pub use version::fetch_version;
 pub use version::VersionInfo;
 pub use zigscient::Zigscient;
-pub use zls::Zls;
+pub use zls::{Compatibility, Zls};
 pub use zls_stable::ZlsStable;
 
 #[derive(Debug, Clone)]
 pub struct AssetInfo {
     pub name: String,
     pub url: Option<String>,
+    pub shasum: Option<String>,
+    pub size: Option<u64>,
 }
 
 pub trait LspProvider {
-    fn config(&self) -> (&'static str, &'static str);
-    fn asset_info(
-        &self,
-        name: &str,
-        platform: zed::Os,
-        arch: zed::Architecture,
-        version: &str,
-    ) -> Result<AssetInfo>;
-    
-    fn binary_name(&self, name: &str, _platform_key: &str) -> String {
-        format!("{}", name)
+    /// Name install directories are prefixed with and the GitHub repository of the releases
+    fn config(&self) -> (&str, &str);
+    fn asset_info(&self, name: &str, platform: &Platform, version: &str) -> Result<AssetInfo>;
+
+    fn binary_name(&self, name: &str, _platform: &Platform) -> String {
+        name.to_string()
+    }
+
+    /// Platform part of the provider's release asset names (e.g. "x86_64-linux-gnu")
+    fn platform_key(&self, platform: &Platform) -> String {
+        platform.triple()
+    }
+
+    /// Exact release asset name, `{version}` is filled in once the release is known
+    ///
+    /// Without one, the first asset containing the platform key that isn't a signature or
+    /// checksum is picked.
+    fn asset_pattern(&self, _platform: &Platform) -> Option<String> {
+        None
+    }
+
+    /// Format the release assets are shipped in, if it can't be guessed from the platform
+    fn archive_format(&self) -> Option<ArchiveFormat> {
+        None
+    }
+
+    /// Minisign key the provider's release artifacts are signed with
+    ///
+    /// Downloads without a valid signature are rejected when this is set.
+    fn public_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Translates the unified `initialization_options` to the keys the server understands
+    fn initialization_options(&self, options: InitializationOptions) -> Result<Value> {
+        options::to_value(ZlsOptions::from(options))
     }
 }
@@ -0,0 +1,93 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use zed_extension_api::serde_json::{self, Map, Value};
+
+/// `initialization_options` as written in the settings
+///
+/// Keys are the same for every provider, the ones that differ between servers are translated
+/// by [`LspProvider::initialization_options`](super::LspProvider::initialization_options).
+/// Everything else is passed through untouched.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct InitializationOptions {
+    /// Automatically discard unused values and similar on save
+    #[serde(default, alias = "force_autofix")]
+    pub enable_autofix: Option<bool>,
+    #[serde(default)]
+    pub zig_exe_path: Option<String>,
+    #[serde(default)]
+    pub zig_lib_path: Option<String>,
+    #[serde(default)]
+    pub build_runner_path: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl InitializationOptions {
+    pub fn from_value(value: Value) -> Result<Self> {
+        serde_json::from_value(value).map_err(|e| {
+            Error::Configuration {
+                message: format!("Invalid initialization_options: {e}"),
+                fix: "initialization_options has to be an object".into(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Options in the ZLS dialect, also understood by its forks
+#[derive(Debug, Serialize)]
+pub struct ZlsOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_autofix: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zig_exe_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zig_lib_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_runner_path: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl From<InitializationOptions> for ZlsOptions {
+    fn from(options: InitializationOptions) -> Self {
+        Self {
+            force_autofix: options.enable_autofix,
+            zig_exe_path: options.zig_exe_path,
+            zig_lib_path: options.zig_lib_path,
+            build_runner_path: options.build_runner_path,
+            other: options.other,
+        }
+    }
+}
+
+/// Options in the zigscient dialect
+#[derive(Debug, Serialize)]
+pub struct ZigscientOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_autofix: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zig_exe_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zig_lib_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_runner_path: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl From<InitializationOptions> for ZigscientOptions {
+    fn from(options: InitializationOptions) -> Self {
+        Self {
+            enable_autofix: options.enable_autofix,
+            zig_exe_path: options.zig_exe_path,
+            zig_lib_path: options.zig_lib_path,
+            build_runner_path: options.build_runner_path,
+            other: options.other,
+        }
+    }
+}
+
+pub fn to_value(options: impl Serialize) -> Result<Value> {
+    serde_json::to_value(options).map_err(|e| Error::SerializationFailed(e.to_string()).into())
+}
@@ -3,48 +3,121 @@ use crate::settings::VersionSource;
 use crate::util::{fetch_json, JsonExt};
 use zed_extension_api::{self as zed, serde_json::Value};
 
+/// Release assets describing another asset rather than being one
+const SIDECAR_EXTENSIONS: [&str; 6] = [".minisig", ".sig", ".asc", ".sha256", ".sha256sum", ".txt"];
+
 #[derive(Debug, Clone)]
 pub struct VersionInfo {
     pub version: String,
     pub download_url: String,
+    pub shasum: Option<String>,
+    pub size: Option<u64>,
 }
 
-pub fn fetch_version(source: &VersionSource, platform: &str) -> Result<VersionInfo> {
+/// Resolves the release to install, the latest one unless `pinned` names a specific version
+///
+/// The asset is the one whose name contains `platform` and isn't a signature or checksum, or
+/// exactly matches `asset_pattern` once its `{version}` placeholder is filled in.
+pub fn fetch_version(
+    source: &VersionSource,
+    platform: &str,
+    pinned: Option<&str>,
+    asset_pattern: Option<&str>,
+) -> Result<VersionInfo> {
     match source {
-        VersionSource::GitHub { url, pre_release } => fetch_github_version(url, *pre_release, platform),
-        VersionSource::ApiEndpoint { url } => fetch_api_version(url, platform),
+        VersionSource::GitHub { url, pre_release } => {
+            fetch_github_version(url, *pre_release, platform, pinned, asset_pattern)
+        }
+        VersionSource::ApiEndpoint { url } => {
+            fetch_api_version(url, platform, pinned, asset_pattern)
+        }
     }
 }
 
-fn fetch_github_version(url: &str, pre_release: bool, platform: &str) -> Result<VersionInfo> {
-    let release = zed::latest_github_release(
-        url,
-        zed::GithubReleaseOptions { require_assets: true, pre_release },
-    )
-    .map_err(|e| Error::LanguageServer(e.to_string()))?;
+fn fetch_github_version(
+    url: &str,
+    pre_release: bool,
+    platform: &str,
+    pinned: Option<&str>,
+    asset_pattern: Option<&str>,
+) -> Result<VersionInfo> {
+    let release = match pinned {
+        Some(tag) => zed::github_release_by_tag_name(url, tag),
+        None => zed::latest_github_release(
+            url,
+            zed::GithubReleaseOptions { require_assets: true, pre_release },
+        ),
+    }
+    .map_err(|e| Error::LanguageServer(format!("Failed to fetch release from {url}: {e}")))?;
 
-    let asset = release
-        .assets
-        .iter()
-        .find(|a| a.name.contains(platform))
-        .ok_or_else(|| Error::AssetNotFound(platform.to_string()))?;
+    let asset = match asset_pattern {
+        Some(pattern) => {
+            // Tags are often prefixed with a `v` that asset names leave out.
+            let names = [&release.version, release.version.trim_start_matches('v')]
+                .map(|version| pattern.replace("{version}", version));
+            release
+                .assets
+                .iter()
+                .find(|a| names.contains(&a.name))
+                .ok_or_else(|| Error::AssetNotFound(names[0].clone()))?
+        }
+        // Signatures and checksums are published next to the archives and share their names.
+        None => release
+            .assets
+            .iter()
+            .filter(|a| !SIDECAR_EXTENSIONS.iter().any(|extension| a.name.ends_with(extension)))
+            .find(|a| a.name.contains(platform))
+            .ok_or_else(|| Error::AssetNotFound(platform.to_string()))?,
+    };
 
     Ok(VersionInfo {
         version: release.version,
         download_url: asset.download_url.clone(),
+        shasum: None,
+        size: None,
     })
 }
 
-fn fetch_api_version(url: &str, platform: &str) -> Result<VersionInfo> {
-    let response: Value = fetch_json(url)?;
-    
+/// Endpoints serving more than one version take it through a `{version}` placeholder in the url
+fn fetch_api_version(
+    url: &str,
+    platform: &str,
+    pinned: Option<&str>,
+    asset_pattern: Option<&str>,
+) -> Result<VersionInfo> {
+    let url = match pinned {
+        Some(pinned) => url.replace("{version}", pinned),
+        None => url.to_string(),
+    };
+    let response: Value = fetch_json(&url)?;
+    let version = response.get_str("version")?;
+    let platform = match asset_pattern {
+        Some(pattern) => pattern.replace("{version}", version),
+        None => platform.to_string(),
+    };
+    let asset = response.get_obj(&platform)?;
+
+    if let Some(pinned) = pinned.filter(|pinned| *pinned != version) {
+        return Err(Error::Configuration {
+            message: format!("{url} returned version {version} instead of {pinned}"),
+            fix: "Add a {version} placeholder to the api url to request the pinned version"
+                .into(),
+        }
+        .into());
+    }
+
     Ok(VersionInfo {
-        version: response.get_str("version")?.to_string(),
-        download_url: response
-            .get_obj(platform)?
+        version: version.to_string(),
+        download_url: asset
             .get("tarball")
             .and_then(|t| t.as_str())
             .ok_or_else(|| Error::Missing { field: format!("{}.tarball", platform) })?
             .to_string(),
+        shasum: asset.get("shasum").and_then(|s| s.as_str()).map(str::to_string),
+        // Both the zigtools API and the Zig index report sizes as strings.
+        size: asset.get("size").and_then(|s| match s {
+            Value::String(s) => s.parse().ok(),
+            s => s.as_u64(),
+        }),
     })
 }
@@ -1,29 +1,33 @@
-use super::{AssetInfo, LspProvider, Result};
-use zed_extension_api::{Architecture, Os};
+use super::{
+    options::{self, ZigscientOptions},
+    AssetInfo, InitializationOptions, LspProvider, Result,
+};
+use crate::platform::Platform;
+use zed_extension_api::serde_json::Value;
 
 #[derive(Debug, Default)]
 pub struct Zigscient;
 
 impl LspProvider for Zigscient {
-    fn config(&self) -> (&'static str, &'static str) {
+    fn config(&self) -> (&str, &str) {
         ("zigscient", "nuIIpointerexception/zigscient-builds")
     }
 
-    fn asset_info(
-        &self,
-        name: &str,
-        _platform: Os,
-        _arch: Architecture,
-        version: &str,
-    ) -> Result<AssetInfo> {
+    fn asset_info(&self, name: &str, _platform: &Platform, version: &str) -> Result<AssetInfo> {
 
         Ok(AssetInfo {
             name: format!("{}-{}", name, version),
             url: None,
+            shasum: None,
+            size: None,
         })
     }
 
-    fn binary_name(&self, name: &str, platform_key: &str) -> String {
-        format!("{}-{}", name, platform_key)
+    fn binary_name(&self, name: &str, platform: &Platform) -> String {
+        format!("{}-{}", name, self.platform_key(platform))
+    }
+
+    fn initialization_options(&self, options: InitializationOptions) -> Result<Value> {
+        options::to_value(ZigscientOptions::from(options))
     }
 }
\ No newline at end of file
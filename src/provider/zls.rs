@@ -1,53 +1,112 @@
-use super::{version, AssetInfo, LspProvider, Result};
-use crate::{settings::VersionSource, util::{url_encode, JsonExt}};
-use zed_extension_api::{Architecture, Os,  serde_json::Value};
-use crate::util::fetch_json;
+use super::{version, AssetInfo, LspProvider, Result, ZlsStable};
+use crate::{
+    platform::Platform,
+    settings::VersionSource,
+    toolchain::{find_release, ZIG_INDEX_URL},
+    util::{fetch_json, url_encode},
+    verify::ZIGTOOLS_PUBLIC_KEY,
+};
+use zed_extension_api::serde_json::Value;
+
+const SELECT_VERSION_URL: &str = "https://releases.zigtools.org/v1/zls/select-version";
+
+/// How closely the selected ZLS build has to match the Zig version
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Compatibility {
+    /// ZLS only has to understand the standard library of that version
+    #[default]
+    OnlyRuntime,
+    /// ZLS has to support the version completely, including its build runner
+    Full,
+}
+
+impl Compatibility {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::OnlyRuntime => "only-runtime",
+            Self::Full => "full",
+        }
+    }
+}
 
 #[derive(Debug, Default)]
-pub struct Zls;
+pub struct Zls {
+    /// Zig version the project needs, master is used when unset
+    pub zig_version: Option<String>,
+    pub compatibility: Compatibility,
+}
 
 impl LspProvider for Zls {
-    fn config(&self) -> (&'static str, &'static str) {
+    fn config(&self) -> (&str, &str) {
         ("zls", "zigtools/zls")
     }
 
-    fn asset_info(
-        &self,
-        name: &str,
-        platform: Os,
-        arch: Architecture,
-        _version: &str,
-    ) -> Result<AssetInfo> {
-        let arch_str = match arch {
-            Architecture::Aarch64 => "aarch64",
-            Architecture::X8664 => "x86_64",
-            Architecture::X86 => "x86",
-        };
+    fn asset_info(&self, name: &str, platform: &Platform, version: &str) -> Result<AssetInfo> {
+        // A pinned zls version is looked up upstream so its url and checksum are known, tagged
+        // releases share their version with the Zig release they support.
+        if !version.is_empty() {
+            let endpoint_url = format!(
+                "{SELECT_VERSION_URL}?zig_version={}&compatibility=full",
+                url_encode(version)
+            );
+            let version_info = version::fetch_version(
+                &VersionSource::ApiEndpoint { url: endpoint_url },
+                &self.platform_key(platform),
+                Some(version),
+                None,
+            )
+            .or_else(|_| {
+                version::fetch_version(
+                    &VersionSource::GitHub { url: "zigtools/zls".into(), pre_release: true },
+                    &self.platform_key(platform),
+                    Some(version),
+                    ZlsStable.asset_pattern(platform).as_deref(),
+                )
+            })?;
+            return Ok(AssetInfo {
+                name: format!("{}-{}", name, version),
+                url: Some(version_info.download_url),
+                shasum: version_info.shasum,
+                size: version_info.size,
+            });
+        }
 
-        let platform_str = match platform {
-            Os::Mac => "macos",
-            Os::Linux => "linux",
-            Os::Windows => "windows",
+        let zig_version = match self.zig_version.as_deref() {
+            Some(version) if version != "master" && version != "stable" => version.to_string(),
+            version => {
+                let zig_response: Value = fetch_json(ZIG_INDEX_URL)?;
+                find_release(&zig_response, version.unwrap_or("master"))?.0
+            }
         };
-
-        let platform_key = format!("{}-{}", arch_str, platform_str);
-        
-        let zig_response: Value = fetch_json("https://ziglang.org/download/index.json")?;
-        let zig_version = zig_response.get_nested_str("master.version")?;
         let endpoint_url = format!(
-            "{}?zig_version={}&compatibility=only-runtime",
-            "https://releases.zigtools.org/v1/zls/select-version",
-            url_encode(zig_version)
+            "{SELECT_VERSION_URL}?zig_version={}&compatibility={}",
+            url_encode(&zig_version),
+            self.compatibility.as_str()
         );
-        let version_info = version::fetch_version(&VersionSource::ApiEndpoint { url: endpoint_url }, &platform_key)?;
+        let version_info = version::fetch_version(
+            &VersionSource::ApiEndpoint { url: endpoint_url },
+            &self.platform_key(platform),
+            None,
+            None,
+        )?;
 
         Ok(AssetInfo {
             name: format!("{}-{}", name, version_info.version),
             url: Some(version_info.download_url),
+            shasum: version_info.shasum,
+            size: version_info.size,
         })
     }
 
-    fn binary_name(&self, name: &str, _platform_key: &str) -> String {
+    fn binary_name(&self, name: &str, _platform: &Platform) -> String {
         name.to_string()
     }
+
+    fn platform_key(&self, platform: &Platform) -> String {
+        platform.zig_key()
+    }
+
+    fn public_key(&self) -> Option<&'static str> {
+        Some(ZIGTOOLS_PUBLIC_KEY)
+    }
 }
@@ -1,29 +1,40 @@
 use super::{AssetInfo, LspProvider, Result};
-use zed_extension_api::{Architecture, Os};
+use crate::{platform::Platform, verify::ZIGTOOLS_PUBLIC_KEY};
 
 #[derive(Debug, Default)]
 pub struct ZlsStable;
 
 impl LspProvider for ZlsStable {
-    fn config(&self) -> (&'static str, &'static str) {
+    fn config(&self) -> (&str, &str) {
         ("zls", "zigtools/zls")
     }
 
-    fn asset_info(
-        &self,
-        name: &str,
-        _platform: Os,
-        _arch: Architecture,
-        version: &str,
-    ) -> Result<AssetInfo> {
+    fn asset_info(&self, name: &str, _platform: &Platform, version: &str) -> Result<AssetInfo> {
 
         Ok(AssetInfo {
             name: format!("{}-{}", name, version),
             url: None,
+            shasum: None,
+            size: None,
         })
     }
 
-    fn binary_name(&self, name: &str, _platform_key: &str) -> String {
+    fn binary_name(&self, name: &str, _platform: &Platform) -> String {
         name.to_string()
     }
+
+    /// ZLS release assets use Zig's target names, e.g. "zls-x86_64-linux.tar.xz"
+    fn platform_key(&self, platform: &Platform) -> String {
+        platform.zig_key()
+    }
+
+    /// Every archive sits next to its `.minisig`, so the name has to match exactly
+    fn asset_pattern(&self, platform: &Platform) -> Option<String> {
+        let extension = if platform.is_windows() { "zip" } else { "tar.xz" };
+        Some(format!("zls-{}.{extension}", self.platform_key(platform)))
+    }
+
+    fn public_key(&self) -> Option<&'static str> {
+        Some(ZIGTOOLS_PUBLIC_KEY)
+    }
 }
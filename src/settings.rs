@@ -1,12 +1,17 @@
+use crate::archive::ArchiveFormat;
+use crate::diagnostics;
 use crate::error::{Error, Result};
+use crate::platform::Platform;
 use crate::util::parse_url;
 use serde::Deserialize;
+use zed_extension_api::serde_json::{self, Map, Value};
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 pub enum Provider {
     #[serde(alias = "zigscient")]
     Zigscient,
+    #[default]
     #[serde(alias = "zls")]
     Zls,
     #[serde(alias = "zls-stable")]
@@ -15,21 +20,8 @@ pub enum Provider {
     Custom,
 }
 
-impl Default for Provider {
-    fn default() -> Self {
-        Self::Zls
-    }
-}
-
-#[derive(Debug, Deserialize, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum SourceType {
-    Github,
-    Api,
-}
-
 #[derive(Debug, Deserialize, Clone)]
-#[serde(rename_all = "snake_case", tag = "type")]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
 pub enum VersionSource {
     #[serde(rename = "github")]
     GitHub {
@@ -41,6 +33,51 @@ pub enum VersionSource {
     ApiEndpoint { url: String },
 }
 
+/// How often installed language servers and compilers are checked for updates
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdatePolicy {
+    Always,
+    #[default]
+    Daily,
+    Weekly,
+    Never,
+}
+
+impl UpdatePolicy {
+    /// Seconds an install is reused before checking again, `None` if it never expires
+    pub fn interval(&self) -> Option<u64> {
+        match self {
+            Self::Always => Some(0),
+            Self::Daily => Some(24 * 60 * 60),
+            Self::Weekly => Some(7 * 24 * 60 * 60),
+            Self::Never => None,
+        }
+    }
+}
+
+impl Provider {
+    /// Provider a language server id from `extension.toml` uses when `provider` isn't set
+    ///
+    /// * `zls` - zls master builds
+    /// * `zigscient` - zigscient, e.g. next to zls
+    pub fn for_language_server(language_server_id: &str) -> Self {
+        match language_server_id {
+            "zigscient" => Self::Zigscient,
+            _ => Self::Zls,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Zigscient => "zigscient",
+            Self::Zls => "zls",
+            Self::ZlsStable => "zls-stable",
+            Self::Custom => "custom",
+        }
+    }
+}
+
 impl VersionSource {
     pub fn validate(&self) -> Result<()> {
         match self {
@@ -61,20 +98,331 @@ impl VersionSource {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Describes the releases of a `custom` provider
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomSettings {
+    /// Name of the language server executable (default: "custom")
+    #[serde(default)]
+    pub binary_name: Option<String>,
+    /// Release asset name with `{version}`, `{os}` and `{arch}` placeholders,
+    /// e.g. "zls-{arch}-{os}-{version}.tar.gz"
+    #[serde(default)]
+    pub asset_pattern: Option<String>,
+    /// "tar.xz", "tar.gz", "zip", "gz" or "none" for a bare executable
+    #[serde(default)]
+    pub archive: Option<ArchiveFormat>,
+    /// Location of the executable inside the archive, e.g. "bin/zls"
+    #[serde(default)]
+    pub binary_path: Option<String>,
+}
+
+/// Names accepted by `provider`
+pub const PROVIDER_NAMES: &[&str] = &["zls", "zls-stable", "zigscient", "custom"];
+
+/// Names accepted by `version_source.type`
+pub const VERSION_SOURCE_TYPES: &[&str] = &["github", "api"];
+
+/// Keys accepted in `lsp.<id>.settings`
+pub const EXTENSION_KEYS: &[&str] = &[
+    "provider",
+    "version_source",
+    "custom",
+    "version",
+    "offline",
+    "update_policy",
+    "keep_versions",
+    "install_zig",
+    "zig_version",
+    "platform",
+    "zls",
+];
+
+/// `lsp.<id>.settings` together with the `binary` section
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ZigTooling {
     #[serde(default)]
     pub provider: Provider,
-    #[serde(default)]
+    #[serde(skip)]
     pub path: Option<String>,
-    #[serde(default)]
+    #[serde(skip)]
     pub args: Option<Vec<String>>,
     #[serde(default)]
     pub version_source: Option<VersionSource>,
+    #[serde(default)]
+    pub custom: CustomSettings,
+    /// Exact language server version (or release tag) to install instead of the latest one
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Never touch the network, only use language servers and compilers installed earlier
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub update_policy: UpdatePolicy,
+    /// Number of language server versions per provider and of zig versions to keep on disk
+    /// (default: 3)
+    #[serde(default)]
+    pub keep_versions: Option<usize>,
+    /// Install a Zig compiler into the extension directory when none is on PATH (default: true)
+    #[serde(default)]
+    pub install_zig: Option<bool>,
+    /// Zig version to install: "master", "stable" or an exact release
+    #[serde(default)]
+    pub zig_version: Option<String>,
+    /// Platform to download builds for, e.g. "x86_64-linux-musl" (default: detected)
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    /// Server options sent whenever the server asks for its configuration, changes apply
+    /// without restarting it
+    #[serde(default)]
+    pub zls: Map<String, Value>,
 }
 
-impl Default for ZigTooling {
-    fn default() -> Self {
-        Self { provider: Provider::default(), path: None, args: None, version_source: None }
+impl ZigTooling {
+    /// Parses `lsp.<id>.settings`, unknown keys and invalid values are errors
+    ///
+    /// `provider` defaults to the one of the language server id.
+    pub fn from_settings(language_server_id: &str, settings: Value) -> Result<Self> {
+        let settings = match settings {
+            Value::Object(settings) => settings,
+            Value::Null => Map::new(),
+            _ => {
+                return Err(Error::Configuration {
+                    message: format!("lsp.{language_server_id}.settings is not an object"),
+                    fix: "Please wrap the settings in {}".into(),
+                }
+                .into())
+            }
+        };
+
+        // Checked one key at a time so the error names the setting at fault.
+        for (key, value) in &settings {
+            let setting = Map::from_iter([(key.clone(), value.clone())]);
+            if let Err(e) = serde_json::from_value::<Self>(Value::Object(setting)) {
+                return Err(setting_error(language_server_id, key, value, e).into());
+            }
+        }
+
+        let has_provider = settings.contains_key("provider");
+        let mut tooling: Self = serde_json::from_value(Value::Object(settings))
+            .map_err(|e| Error::Settings(e.to_string()))?;
+        if !has_provider {
+            tooling.provider = Provider::for_language_server(language_server_id);
+        }
+        Ok(tooling)
+    }
+}
+
+fn setting_error(
+    language_server_id: &str,
+    key: &str,
+    value: &Value,
+    error: serde_json::Error,
+) -> Error {
+    let section = format!("lsp.{language_server_id}.settings");
+    let example = |key: &str| diagnostics::example(language_server_id, key, example_value(key));
+
+    if !EXTENSION_KEYS.contains(&key) {
+        let suggestion = diagnostics::closest(key, EXTENSION_KEYS).first().copied();
+        return Error::Configuration {
+            message: format!("Unknown setting \"{key}\" in {section}"),
+            fix: format!(
+                "{} Options for the language server itself go into \"zls\" or \
+                 initialization_options, e.g.\n{}",
+                diagnostics::did_you_mean(key, EXTENSION_KEYS),
+                example(suggestion.unwrap_or("zls"))
+            ),
+        };
+    }
+
+    let unknown_name = match (key, value) {
+        ("provider", Value::String(name)) => Some(("provider", name.as_str(), PROVIDER_NAMES)),
+        ("version_source", Value::Object(source)) => match source.get("type") {
+            Some(Value::String(kind)) if !VERSION_SOURCE_TYPES.contains(&kind.as_str()) => {
+                Some(("version_source type", kind.as_str(), VERSION_SOURCE_TYPES))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some((what, name, candidates)) = unknown_name {
+        let value = match (key, diagnostics::closest(name, candidates).first()) {
+            ("provider", Some(&provider)) => provider.into(),
+            ("version_source", Some(&"api")) => {
+                serde_json::json!({ "type": "api", "url": "https://example.com/zls/{version}" })
+            }
+            _ => example_value(key),
+        };
+        return Error::Configuration {
+            message: format!("Unknown {what} \"{name}\" in {section}"),
+            fix: format!(
+                "{} e.g.\n{}",
+                diagnostics::did_you_mean(name, candidates),
+                diagnostics::example(language_server_id, key, value)
+            ),
+        };
+    }
+
+    // Nested fields and enum values are only known to serde, which lists them in its message.
+    let suggestion = unknown_serde_name(&error.to_string()).map(|(name, candidates)| {
+        let candidates = candidates.iter().map(String::as_str).collect::<Vec<_>>();
+        format!("{} ", diagnostics::did_you_mean(&name, &candidates))
+    });
+    Error::Configuration {
+        message: format!("Invalid value for \"{key}\" in {section}: {error}"),
+        fix: format!("{}It is set like this:\n{}", suggestion.unwrap_or_default(), example(key)),
+    }
+}
+
+/// Name and candidates of serde's "unknown field `x`, expected one of `a`, `b`" style errors
+fn unknown_serde_name(error: &str) -> Option<(String, Vec<String>)> {
+    let rest = error.strip_prefix("unknown field `").or(error.strip_prefix("unknown variant `"))?;
+    let (name, expected) = rest.split_once('`')?;
+    let candidates = expected.split('`').skip(1).step_by(2).map(str::to_string).collect::<Vec<_>>();
+    (!candidates.is_empty()).then(|| (name.to_string(), candidates))
+}
+
+/// Value of a setting shown in configuration errors
+pub fn example_value(key: &str) -> Value {
+    match key {
+        "provider" => "zls".into(),
+        "version_source" => serde_json::json!({ "type": "github", "url": "owner/zls-fork" }),
+        "custom" => serde_json::json!({
+            "binary_name": "zls-fork",
+            "asset_pattern": "zls-fork-{arch}-{os}-{version}.tar.gz",
+        }),
+        "version" => "0.13.0".into(),
+        "offline" => false.into(),
+        "update_policy" => "daily".into(),
+        "keep_versions" => 3.into(),
+        "install_zig" => true.into(),
+        "zig_version" => "master".into(),
+        "platform" => "x86_64-linux-musl".into(),
+        "zls" => serde_json::json!({ "enable_build_on_save": true }),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    fn parse_error(language_server_id: &str, settings: Value) -> String {
+        ZigTooling::from_settings(language_server_id, settings).unwrap_err()
+    }
+
+    #[test]
+    fn suggests_known_keys_for_unknown_ones() {
+        let error = parse_error("zls", json!({ "provider": "zls", "provder": "zls-stable" }));
+        assert!(error.starts_with("Configuration error: Unknown setting \"provder\""), "{error}");
+        assert!(error.contains("Did you mean \"provider\"?"), "{error}");
+        assert!(error.contains("\"provider\": \"zls\""), "{error}");
+    }
+
+    #[test]
+    fn suggests_known_nested_fields() {
+        let error = parse_error("zls", json!({ "custom": { "binary_nme": "zls-fork" } }));
+        assert!(error.contains("Invalid value for \"custom\" in lsp.zls.settings"), "{error}");
+        assert!(error.contains("unknown field `binary_nme`"), "{error}");
+        assert!(error.contains("Did you mean \"binary_name\"?"), "{error}");
+    }
+
+    #[test]
+    fn suggests_known_enum_values() {
+        let error = parse_error("zls", json!({ "update_policy": "dailly" }));
+        assert!(error.contains("Invalid value for \"update_policy\""), "{error}");
+        assert!(error.contains("Did you mean \"daily\"?"), "{error}");
+
+        let error = parse_error("zls", json!({ "provider": "zls-stabel" }));
+        assert!(error.contains("Unknown provider \"zls-stabel\""), "{error}");
+        assert!(error.contains("Did you mean \"zls-stable\"?"), "{error}");
+
+        let source = json!({ "type": "gihub", "url": "a/b" });
+        let error = parse_error("zls", json!({ "version_source": source }));
+        assert!(error.contains("Unknown version_source type \"gihub\""), "{error}");
+        assert!(error.contains("Did you mean \"github\"?"), "{error}");
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        let error = parse_error("zls", json!({ "keep_versions": -1 }));
+        assert!(error.contains("Invalid value for \"keep_versions\""), "{error}");
+        assert!(error.contains("\"keep_versions\": 3"), "{error}");
+    }
+
+    #[test]
+    fn reports_invalid_platform_once() {
+        let error = parse_error("zls", json!({ "platform": "x86_64-darwin" }));
+        assert_eq!(error.matches("Configuration error").count(), 1, "{error}");
+        assert!(error.contains("Unsupported operating system \"darwin\""), "{error}");
+    }
+
+    #[test]
+    fn rejects_settings_that_are_not_an_object() {
+        for settings in [json!("zls"), json!(["provider"]), json!(1)] {
+            let error = parse_error("zigscient", settings);
+            assert!(error.contains("lsp.zigscient.settings is not an object"), "{error}");
+        }
+    }
+
+    #[test]
+    fn provider_defaults_to_the_language_server() {
+        for (id, settings, provider) in [
+            ("zls", json!({}), "zls"),
+            ("zls", Value::Null, "zls"),
+            ("zigscient", json!({ "offline": true }), "zigscient"),
+            ("zigscient", json!({ "provider": "zls-stable" }), "zls-stable"),
+            ("zls", json!({ "provider": "zigscient" }), "zigscient"),
+        ] {
+            let tooling = ZigTooling::from_settings(id, settings).unwrap();
+            assert_eq!(tooling.provider.as_str(), provider, "{id}");
+        }
+    }
+
+    #[test]
+    fn parses_every_setting() {
+        let tooling = ZigTooling::from_settings(
+            "zls",
+            json!({
+                "provider": "custom",
+                "version_source": { "type": "github", "url": "owner/zls", "pre_release": true },
+                "custom": { "binary_name": "zls-fork", "archive": "zip", "binary_path": "bin/zls" },
+                "version": "0.13.0",
+                "offline": true,
+                "update_policy": "never",
+                "keep_versions": 1,
+                "install_zig": false,
+                "zig_version": "stable",
+                "platform": "aarch64-linux-musl",
+                "zls": { "enable_build_on_save": true },
+            }),
+        )
+        .unwrap();
+        assert_eq!(tooling.provider.as_str(), "custom");
+        assert!(matches!(
+            tooling.version_source,
+            Some(VersionSource::GitHub { url, pre_release: true }) if url == "owner/zls"
+        ));
+        assert_eq!(tooling.custom.archive, Some(ArchiveFormat::Zip));
+        assert_eq!(tooling.version.as_deref(), Some("0.13.0"));
+        assert!(tooling.offline);
+        assert_eq!(tooling.update_policy, UpdatePolicy::Never);
+        assert_eq!(tooling.keep_versions, Some(1));
+        assert_eq!(tooling.install_zig, Some(false));
+        assert_eq!(tooling.platform.unwrap().to_string(), "aarch64-linux-musl");
+        assert_eq!(tooling.zls.get("enable_build_on_save"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn reads_candidates_from_serde_errors() {
+        let error = "unknown variant `dailly`, expected one of `always`, `daily`, `weekly`";
+        let (name, candidates) = unknown_serde_name(error).unwrap();
+        assert_eq!(name, "dailly");
+        assert_eq!(candidates, ["always", "daily", "weekly"]);
+        assert_eq!(unknown_serde_name("unknown field `x`, expected `y`").unwrap().1, ["y"]);
+        assert!(unknown_serde_name("unknown field `x`, there are no fields").is_none());
+        assert!(unknown_serde_name("invalid type: integer `1`, expected a string").is_none());
     }
 }
@@ -0,0 +1,188 @@
+use crate::{
+    archive::{download_verified, ArchiveFormat},
+    error::{Error, Result},
+    manifest::{self, InstallRecord, Manifest},
+    platform::Platform,
+    util::{compare_versions, fetch_json, JsonExt},
+    verify::{fetch_signature, Integrity},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use zed_extension_api::{self as zed, serde_json::Value, LanguageServerId};
+
+pub const ZIG_INDEX_URL: &str = "https://ziglang.org/download/index.json";
+
+/// Minisign public key the Zig Software Foundation signs release tarballs with
+///
+/// Taken from https://ziglang.org/download
+pub const ZIG_PUBLIC_KEY: &str = "RWSGOq2NVecA2UPNdBUZykf1CCb147pkmdtYxgb3Ti+JO/wCYvhbAb/U";
+
+/// A Zig compiler installed into the extension work directory
+#[derive(Debug, Clone)]
+pub struct Toolchain {
+    /// Directory containing the `zig` executable and its `lib` folder
+    pub root: PathBuf,
+}
+
+impl Toolchain {
+    pub fn from_record(record: &InstallRecord) -> Option<Self> {
+        Some(Self { root: Path::new(&record.binary_path).parent()?.to_path_buf() })
+    }
+
+    pub fn zig_exe_path(&self) -> PathBuf {
+        self.root.join(zig_exe_name())
+    }
+
+    pub fn zig_lib_path(&self) -> PathBuf {
+        self.root.join("lib")
+    }
+}
+
+fn zig_exe_name() -> &'static str {
+    match zed::current_platform().0 {
+        zed::Os::Windows => "zig.exe",
+        _ => "zig",
+    }
+}
+
+/// Looks up a release in the Zig download index
+///
+/// * "master" resolves to the latest nightly build
+/// * "stable" resolves to the newest tagged release
+/// * anything else has to be an exact release version (e.g. "0.13.0")
+///
+/// Returns the concrete version together with its index entry.
+pub fn find_release<'a>(index: &'a Value, version: &str) -> Result<(String, &'a Value)> {
+    let entries = index.as_object().ok_or_else(|| Error::Missing { field: "releases".into() })?;
+
+    let (key, entry) = match version {
+        "stable" => entries
+            .iter()
+            .filter(|(key, _)| key.as_str() != "master")
+            .max_by(|(a, _), (b, _)| compare_versions(a, b))
+            .ok_or_else(|| Error::Missing { field: "stable release".into() })?,
+        version => entries
+            .get_key_value(version)
+            .ok_or_else(|| Error::AssetNotFound(format!("Zig {version} is not in the index")))?,
+    };
+
+    let version = entry.get_str("version").map(str::to_string).unwrap_or_else(|_| key.clone());
+    Ok((version, entry))
+}
+
+/// Returns the Zig compiler for `version`, downloading it on first use
+///
+/// Every version lives in its own `zig-<version>` directory so multiple projects can share
+/// them. The download is shown as the status of `language_server_id`, if there is one.
+pub fn install(
+    version: &str,
+    channel: &str,
+    platform: &Platform,
+    language_server_id: Option<&LanguageServerId>,
+) -> Result<InstallRecord> {
+    let index = fetch_json(ZIG_INDEX_URL)?;
+    // Nightly builds vanish from the index, master satisfies any pinned dev version though.
+    let (version, entry) = match find_release(&index, version) {
+        Err(_) if version.contains('-') => find_release(&index, "master")?,
+        release => release?,
+    };
+
+    let platform_key = platform.zig_key();
+    let asset = entry
+        .get(&platform_key)
+        .ok_or_else(|| Error::AssetNotFound(format!("No Zig {version} build for {platform_key}")))?;
+    let tarball = asset.get_str("tarball")?;
+    let shasum = asset.get_str("shasum").ok().map(str::to_string);
+    let now = manifest::now();
+
+    let version_dir = PathBuf::from(format!("zig-{version}"));
+    if let Some(root) = find_toolchain_root(&version_dir) {
+        let installed_at = Manifest::load()
+            .installs
+            .iter()
+            .find(|record| Path::new(&record.binary_path) == root.join(zig_exe_name()))
+            .map_or(now, |record| record.installed_at);
+        return Ok(record(channel, version, tarball, shasum, installed_at, &root));
+    }
+
+    if let Some(language_server_id) = language_server_id {
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Downloading,
+        );
+    }
+
+    fs::create_dir_all(&version_dir).map_err(|e| {
+        Error::InstallationFailed(format!(
+            "Failed to create directory {}: {e}",
+            version_dir.display()
+        ))
+    })?;
+
+    let integrity = Integrity {
+        shasum,
+        size: asset.get_str("size").ok().and_then(|size| size.parse().ok()),
+        signature: Some(fetch_signature(tarball)?),
+        public_key: Some(ZIG_PUBLIC_KEY),
+    };
+    let format = ArchiveFormat::detect(tarball, None)?;
+    let checksum = download_verified(tarball, &version_dir, format, zig_exe_name(), &integrity)?;
+
+    let root = find_toolchain_root(&version_dir).ok_or_else(|| {
+        Error::InstallationFailed(format!("No zig executable found in {}", version_dir.display()))
+    })?;
+    zed::make_file_executable(&root.join(zig_exe_name()).to_string_lossy())?;
+
+    Ok(record(channel, version, tarball, Some(checksum), now, &root))
+}
+
+fn record(
+    channel: &str,
+    version: String,
+    tarball: &str,
+    checksum: Option<String>,
+    installed_at: u64,
+    root: &Path,
+) -> InstallRecord {
+    InstallRecord {
+        provider: "zig".into(),
+        channel: channel.into(),
+        version,
+        source_url: tarball.into(),
+        checksum,
+        installed_at,
+        checked_at: manifest::now(),
+        binary_path: root.join(zig_exe_name()).to_string_lossy().into(),
+    }
+}
+
+/// Newest Zig compiler that was installed previously, used when we can't reach the index
+pub fn find_installed() -> Option<Toolchain> {
+    fs::read_dir(".")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let dir = entry.file_name().into_string().ok()?;
+            let version = dir.strip_prefix("zig-")?.to_string();
+            let root = find_toolchain_root(Path::new(&dir))?;
+            Some((version, root))
+        })
+        .max_by(|(a, _), (b, _)| compare_versions(a, b))
+        .map(|(_, root)| Toolchain { root })
+}
+
+/// Zig tarballs unpack into a single `zig-<platform>-<version>` directory, so the compiler is
+/// either directly in `version_dir` or one level below it.
+fn find_toolchain_root(version_dir: &Path) -> Option<PathBuf> {
+    if version_dir.join(zig_exe_name()).is_file() {
+        return Some(version_dir.to_path_buf());
+    }
+
+    fs::read_dir(version_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.join(zig_exe_name()).is_file())
+}
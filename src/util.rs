@@ -1,4 +1,5 @@
 use crate::error::{Error, Result};
+use std::{cmp::Ordering, path::Path};
 use zed_extension_api::{
     http_client,
     http_client::{HttpMethod, HttpRequestBuilder},
@@ -30,10 +31,120 @@ pub fn url_encode(text: &str) -> String {
     encoded
 }
 
-/// Fetches and parses JSON from a given URL
+/// Expands `~`, `$VAR`, `${VAR}` and `${worktree}` the way a shell would
 ///
-/// Makes a GET request to the provided URL and attempts to parse the response as JSON
-pub fn fetch_json(url: &str) -> Result<serde_json::Value> {
+/// Variables come from `env` (the worktree's shell environment), `${worktree}` is the worktree
+/// root. Unknown variables are left as they are.
+pub fn expand_vars(text: &str, env: &[(String, String)], worktree_root: &str) -> String {
+    let lookup = |name: &str| match name {
+        "worktree" => Some(worktree_root),
+        name => env.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str()),
+    };
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        if let Some(home) = lookup("HOME").or_else(|| lookup("USERPROFILE")) {
+            expanded.push_str(home);
+            rest = &rest[1 ..];
+        }
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[.. start]);
+        let after = &rest[start + 1 ..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => braced.find('}').map_or(("", 0), |end| (&braced[.. end], end + 2)),
+            None => {
+                let end = after
+                    .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+                    .unwrap_or(after.len());
+                (&after[.. end], end)
+            }
+        };
+
+        match lookup(name).filter(|_| !name.is_empty()) {
+            Some(value) => {
+                expanded.push_str(value);
+                rest = &after[len ..];
+            }
+            None => {
+                expanded.push('$');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Resolves a relative path against `root`, absolute paths (`/`, `C:\`, `\\server`) are kept
+pub fn resolve_path(path: &str, root: &str) -> String {
+    let has_drive = path.as_bytes().first().is_some_and(u8::is_ascii_alphabetic)
+        && matches!(path.get(1 .. 3), Some(":\\" | ":/"));
+    if has_drive || path.starts_with(['/', '\\']) {
+        return path.to_string();
+    }
+
+    let path = path.strip_prefix("./").unwrap_or(path);
+    Path::new(root).join(path).to_string_lossy().into_owned()
+}
+
+/// Finds a Zig version (e.g. "0.13.0" or "0.14.0-dev.1911+3bf89f55c") inside a path
+///
+/// Path components are searched from the end, so the directory closest to the executable wins.
+pub fn version_from_path(path: &str) -> Option<String> {
+    path.rsplit(['/', '\\']).find_map(|component| {
+        component.char_indices().find_map(|(start, c)| {
+            let previous = component[.. start].chars().last();
+            if !c.is_ascii_digit() || previous.is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                return None;
+            }
+
+            let rest = &component[start ..];
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && !matches!(c, '.' | '-' | '+'))
+                .unwrap_or(rest.len());
+            let candidate = &rest[.. len];
+            // Build metadata is a commit hash, anything after it is a file extension.
+            let candidate = match candidate.find('+') {
+                Some(plus) => &candidate[.. candidate[plus ..].find('.').map_or(len, |i| plus + i)],
+                None => candidate,
+            };
+            let candidate = candidate.trim_end_matches(['.', '-', '+']);
+            let (release, pre) = candidate.split_once('-').unwrap_or((candidate, ""));
+
+            let is_number =
+                |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            let parts = release.split('.').collect::<Vec<_>>();
+            let valid_release = parts.len() == 3 && parts.iter().all(|part| is_number(part));
+            let valid_pre = pre.is_empty() || pre.starts_with("dev.");
+
+            (valid_release && valid_pre).then(|| candidate.to_string())
+        })
+    })
+}
+
+/// Orders version strings the way Zig releases are ordered
+///
+/// Release parts are compared numerically, a dev build sorts before the release it leads up
+/// to and build metadata (`+<commit>`) is ignored.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn key(version: &str) -> (Vec<u64>, bool, u64) {
+        let version = version.split('+').next().unwrap_or(version);
+        let (release, pre) = version.split_once('-').unwrap_or((version, ""));
+        let parts = release.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+        let dev = pre.rsplit('.').next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        (parts, pre.is_empty(), dev)
+    }
+
+    key(a).cmp(&key(b))
+}
+
+/// Fetches the raw response body of a given URL
+///
+/// Makes a GET request to the provided URL and returns the body untouched
+pub fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
     let request = HttpRequestBuilder::new()
         .method(HttpMethod::Get)
         .url(url)
@@ -41,7 +152,14 @@ pub fn fetch_json(url: &str) -> Result<serde_json::Value> {
         .map_err(|e| Error::FetchFailed { url: url.to_string(), error: e.to_string() })?;
     let response = http_client::fetch(&request)
         .map_err(|e| Error::FetchFailed { url: url.to_string(), error: e.to_string() })?;
-    serde_json::from_slice(&response.body)
+    Ok(response.body)
+}
+
+/// Fetches and parses JSON from a given URL
+///
+/// Makes a GET request to the provided URL and attempts to parse the response as JSON
+pub fn fetch_json(url: &str) -> Result<serde_json::Value> {
+    serde_json::from_slice(&fetch_bytes(url)?)
         .map_err(|e| format!("Failed to parse JSON response: {}", e))
 }
 
@@ -62,6 +180,232 @@ pub fn parse_url(url: &str) -> Result<()> {
     Ok(())
 }
 
+/// Parses a ZON document (e.g. `build.zig.zon`) into a JSON value
+///
+/// * `.{ .field = value }` becomes an object, `.{ a, b }` an array
+/// * enum literals (`.foo`) and bare identifiers become strings
+/// * `//` comments are skipped
+///
+/// Only the subset of ZON used by package manifests is supported.
+pub fn parse_zon(source: &str) -> Result<Value> {
+    let mut parser = ZonParser { src: source, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < source.len() {
+        return Err(parser.error("Unexpected trailing input"));
+    }
+    Ok(value)
+}
+
+struct ZonParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> ZonParser<'a> {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid ZON at offset {}: {}", self.pos, message)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos ..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        Err(self.error(&format!("Expected '{}'", c)))
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with("//") {
+                break;
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('.') => {
+                self.bump();
+                if self.eat('{') {
+                    self.container()
+                } else {
+                    Ok(Value::String(self.identifier()?))
+                }
+            }
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('\\') => Ok(Value::String(self.multiline_string())),
+            Some('@') => Ok(Value::String(self.identifier()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(self.number()),
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                Ok(match self.identifier()?.as_str() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "null" => Value::Null,
+                    ident => Value::String(ident.to_string()),
+                })
+            }
+            _ => Err(self.error("Expected a value")),
+        }
+    }
+
+    /// Parses the body of `.{ ... }` after the opening brace
+    fn container(&mut self) -> Result<Value> {
+        let is_struct = {
+            let start = self.pos;
+            let is_field = self.eat('.') && self.identifier().is_ok() && self.eat('=');
+            self.pos = start;
+            is_field
+        };
+
+        let mut fields = serde_json::Map::new();
+        let mut items = Vec::new();
+        while !self.eat('}') {
+            if is_struct {
+                self.expect('.')?;
+                let name = self.identifier()?;
+                self.expect('=')?;
+                fields.insert(name, self.value()?);
+            } else {
+                items.push(self.value()?);
+            }
+            if !self.eat(',') {
+                self.expect('}')?;
+                break;
+            }
+        }
+
+        Ok(if is_struct || items.is_empty() { Value::Object(fields) } else { Value::Array(items) })
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        if self.rest().starts_with("@\"") {
+            self.bump();
+            return self.string();
+        }
+        let len = self
+            .rest()
+            .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("Expected an identifier"));
+        }
+        let ident = self.rest()[.. len].to_string();
+        self.pos += len;
+        Ok(ident)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.bump().ok_or_else(|| self.error("Unterminated string"))? {
+                '"' => return Ok(string),
+                '\\' => match self.bump().ok_or_else(|| self.error("Unterminated string"))? {
+                    'n' => string.push('\n'),
+                    'r' => string.push('\r'),
+                    't' => string.push('\t'),
+                    'x' => {
+                        let hex = self.rest().get(.. 2).ok_or_else(|| self.error("Bad escape"))?;
+                        let byte =
+                            u8::from_str_radix(hex, 16).map_err(|_| self.error("Bad escape"))?;
+                        string.push(byte as char);
+                        self.pos += 2;
+                    }
+                    'u' => {
+                        if !self.rest().starts_with('{') {
+                            return Err(self.error("Bad escape"));
+                        }
+                        let end = self.rest().find('}').ok_or_else(|| self.error("Bad escape"))?;
+                        let code = u32::from_str_radix(&self.rest()[1 .. end], 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error("Bad escape"))?;
+                        string.push(code);
+                        self.pos += end + 1;
+                    }
+                    c => string.push(c),
+                },
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Consecutive `\\` lines form a single string joined by newlines
+    fn multiline_string(&mut self) -> String {
+        let mut lines = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let Some(line) = self.rest().strip_prefix("\\\\") else {
+                break;
+            };
+            let line = &line[.. line.find('\n').unwrap_or(line.len())];
+            lines.push(line.trim_end_matches('\r').to_string());
+            self.pos += 2 + line.len();
+        }
+        lines.join("\n")
+    }
+
+    fn number(&mut self) -> Value {
+        let len = self
+            .rest()
+            .find(|c: char| !matches!(c, '-' | '+' | '.' | '_') && !c.is_ascii_alphanumeric())
+            .unwrap_or(self.rest().len());
+        let literal = self.rest()[.. len].replace('_', "");
+        self.pos += len;
+
+        let (sign, digits) = match literal.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, literal.as_str()),
+        };
+        let radix = match digits.get(.. 2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            if let Ok(n) = i64::from_str_radix(&digits[2 ..], radix) {
+                return Value::from(sign * n);
+            }
+        } else if let Ok(n) = literal.parse::<i64>() {
+            return Value::from(n);
+        } else if let Ok(n) = literal.parse::<f64>() {
+            return Value::from(n);
+        }
+        Value::String(literal)
+    }
+}
+
 /// Extension trait for serde_json::Value to simplify JSON field extraction
 ///
 /// Provides convenient methods to extract typed values from JSON with proper error handling.
@@ -69,9 +413,6 @@ pub trait JsonExt {
     /// Extracts a string field from a JSON value
     fn get_str(&self, field: &str) -> Result<&str>;
     
-    /// Extracts a nested string field using dot notation (e.g. "parent.child")
-    fn get_nested_str(&self, path: &str) -> Result<&str>;
-    
     /// Extracts an object field from a JSON value
     fn get_obj(&self, field: &str) -> Result<&serde_json::Map<String, Value>>;
 }
@@ -83,17 +424,6 @@ impl JsonExt for serde_json::Value {
             .ok_or_else(|| format!("Missing field: {}", field))
     }
 
-    fn get_nested_str(&self, path: &str) -> Result<&str> {
-        let parts: Vec<&str> = path.split('.').collect();
-        let mut current = self;
-        
-        for (i, &part) in parts.iter().enumerate() {
-            current = current.get(part).ok_or_else(|| format!("Missing field: {}", parts[..=i].join(".")))?;
-        }
-        
-        current.as_str().ok_or_else(|| format!("Missing field: {}", path))
-    }
-
     fn get_obj(&self, field: &str) -> Result<&serde_json::Map<String, Value>> {
         self.get(field)
             .and_then(|v| v.as_object())
@@ -101,4 +431,181 @@ impl JsonExt for serde_json::Value {
     }
 }
 
+/// Empty directory for a test, removed again when the test passes
+#[cfg(test)]
+pub struct TempDir(pub std::path::PathBuf);
 
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("zed-zig-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn touch(&self, path: &str) {
+        let path = self.0.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    #[test]
+    fn expands_variables() {
+        let env = [("HOME", "/home/u"), ("ZLS_DIR", "/opt/zls"), ("EMPTY", "")]
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+
+        for (text, expanded) in [
+            ("~/bin/zls", "/home/u/bin/zls"),
+            ("~", "/home/u"),
+            ("~user/zls", "~user/zls"),
+            ("bin/~/zls", "bin/~/zls"),
+            ("$HOME/.local/bin/zls", "/home/u/.local/bin/zls"),
+            ("${HOME}/zls", "/home/u/zls"),
+            ("${ZLS_DIR}zls", "/opt/zlszls"),
+            ("$ZLS_DIR-$HOME", "/opt/zls-/home/u"),
+            ("${worktree}/zls.json", "/project/zls.json"),
+            ("$EMPTY/zls", "/zls"),
+            ("$MISSING/zls", "$MISSING/zls"),
+            ("${MISSING}", "${MISSING}"),
+            ("${HOME", "${HOME"),
+            ("price$", "price$"),
+            ("$$HOME", "$/home/u"),
+            ("zls-{version}.tar.gz", "zls-{version}.tar.gz"),
+        ] {
+            assert_eq!(expand_vars(text, &env, "/project"), expanded, "{text}");
+        }
+    }
+
+    #[test]
+    fn falls_back_to_userprofile_for_home() {
+        let env = [("USERPROFILE".to_string(), "C:\\Users\\u".to_string())];
+        assert_eq!(expand_vars("~\\zls.exe", &env, ""), "C:\\Users\\u\\zls.exe");
+        assert_eq!(expand_vars("~/zls", &[], ""), "~/zls");
+    }
+
+    #[test]
+    fn resolves_relative_paths() {
+        for (path, resolved) in [
+            ("/usr/bin/zls", "/usr/bin/zls"),
+            ("C:\\zls\\zls.exe", "C:\\zls\\zls.exe"),
+            ("c:/zls/zls.exe", "c:/zls/zls.exe"),
+            ("\\\\server\\zls.exe", "\\\\server\\zls.exe"),
+            ("./bin/zls", "/project/bin/zls"),
+            ("bin/zls", "/project/bin/zls"),
+            ("zls", "/project/zls"),
+            ("../zls", "/project/../zls"),
+        ] {
+            assert_eq!(resolve_path(path, "/project"), resolved, "{path}");
+        }
+    }
+
+    #[test]
+    fn finds_versions_in_paths() {
+        for (path, version) in [
+            ("/home/u/.zvm/0.13.0/zig", Some("0.13.0")),
+            ("/opt/zig-linux-x86_64-0.14.0/zig", Some("0.14.0")),
+            ("C:\\zig\\zig-windows-x86_64-0.12.1\\zig.exe", Some("0.12.1")),
+            ("/zig-linux-x86_64-0.14.0-dev.1911+3bf89f55c/zig", Some("0.14.0-dev.1911+3bf89f55c")),
+            ("/zig/0.11.0/0.13.0/zig", Some("0.13.0")),
+            ("/home/u/.local/share/zigup/0.13.0/files/zig", Some("0.13.0")),
+            ("/usr/bin/zig", None),
+            ("/opt/homebrew/bin/zig", None),
+            ("/zig-1.2/zig", None),
+            ("/zig-0.14.0-rc1/zig", None),
+            ("/x10.0.0.1/zig", None),
+        ] {
+            assert_eq!(version_from_path(path).as_deref(), version, "{path}");
+        }
+    }
+
+    #[test]
+    fn orders_versions() {
+        let mut versions =
+            vec!["0.14.0", "0.9.1", "0.14.0-dev.10+abc", "0.13.0", "0.14.0-dev.2+def", "0.10.0"];
+        versions.sort_by(|a, b| compare_versions(a, b));
+        assert_eq!(
+            versions,
+            ["0.9.1", "0.10.0", "0.13.0", "0.14.0-dev.2+def", "0.14.0-dev.10+abc", "0.14.0"]
+        );
+        assert_eq!(compare_versions("0.14.0-dev.2+abc", "0.14.0-dev.2+def"), Ordering::Equal);
+    }
+
+    #[test]
+    fn parses_build_zig_zon() {
+        let source = r#"
+            // comment
+            .{
+                .name = .example,
+                .version = "0.1.0",
+                .minimum_zig_version = "0.14.0-dev.2+abc",
+                .fingerprint = 0x1234_abcd,
+                .dependencies = .{
+                    .zls = .{ .url = "https://example.com/zls.tar.gz", .lazy = true },
+                },
+                .paths = .{ "build.zig", "src", @"with space" },
+                .empty = .{},
+                .text =
+                    \\first
+                    \\second
+                ,
+                .negative = -12,
+                .float = 1.5,
+                .nothing = null,
+            }
+        "#;
+
+        assert_eq!(
+            parse_zon(source).unwrap(),
+            json!({
+                "name": "example",
+                "version": "0.1.0",
+                "minimum_zig_version": "0.14.0-dev.2+abc",
+                "fingerprint": 0x1234abcd,
+                "dependencies": {
+                    "zls": { "url": "https://example.com/zls.tar.gz", "lazy": true },
+                },
+                "paths": ["build.zig", "src", "with space"],
+                "empty": {},
+                "text": "first\nsecond",
+                "negative": -12,
+                "float": 1.5,
+                "nothing": null,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_string_escapes() {
+        assert_eq!(parse_zon(r#""a\nb\t\"\x41\u{1F600}""#).unwrap(), json!("a\nb\t\"A\u{1F600}"));
+    }
+
+    #[test]
+    fn rejects_invalid_zon() {
+        for source in [
+            "",
+            ".{ .a = }",
+            ".{ .a = 1",
+            r#""unterminated"#,
+            r#""\u}""#,
+            r#""\u{zz}""#,
+            r#""\x4""#,
+            ".{} trailing",
+        ] {
+            assert!(parse_zon(source).is_err(), "{source:?} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    error::{Error, Result},
+    util::fetch_bytes,
+};
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+
+/// Minisign public key zigtools signs every ZLS release artifact with
+///
+/// Taken from https://github.com/zigtools/zls/releases
+pub const ZIGTOOLS_PUBLIC_KEY: &str = "RWR+9B91GBZ0zOjh6Lr17+zKf5BoSuFvrx2xSeDE57uIYvnKBGmMjOex";
+
+/// Expected properties of a downloaded archive
+///
+/// Every field is optional, only the checks we have data for are performed.
+#[derive(Debug, Clone, Default)]
+pub struct Integrity {
+    pub shasum: Option<String>,
+    pub size: Option<u64>,
+    pub signature: Option<String>,
+    pub public_key: Option<&'static str>,
+}
+
+impl Integrity {
+    /// Verifies an archive against everything we know about it and returns its checksum
+    ///
+    /// The archive is read piece by piece, it never has to fit into memory as a whole. A
+    /// provider with a pinned public key must always ship a signature.
+    pub fn verify(&self, mut archive: impl Read) -> Result<String> {
+        let key_and_signature = match (self.public_key, &self.signature) {
+            (Some(public_key), Some(signature)) => Some((
+                PublicKey::from_base64(public_key)
+                    .map_err(|e| Error::VerificationFailed(format!("Invalid public key: {e}")))?,
+                Signature::decode(signature)
+                    .map_err(|e| Error::VerificationFailed(format!("Invalid signature: {e}")))?,
+            )),
+            (Some(_), None) => {
+                return Err(
+                    Error::VerificationFailed("No minisign signature available".into()).into()
+                );
+            }
+            (None, _) => None,
+        };
+        let mut verifier = key_and_signature
+            .as_ref()
+            .map(|(public_key, signature)| public_key.verify_stream(signature))
+            .transpose()
+            .map_err(|e| Error::VerificationFailed(format!("Signature mismatch: {e}")))?;
+
+        let (mut hasher, mut size) = (Sha256::new(), 0);
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = archive.read(&mut buffer).map_err(Error::from)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[.. read]);
+            if let Some(verifier) = &mut verifier {
+                verifier.update(&buffer[.. read]);
+            }
+            size += read as u64;
+        }
+
+        if let Some(expected) = self.size.filter(|&expected| expected != size) {
+            return Err(Error::VerificationFailed(format!(
+                "Size mismatch: expected {expected} bytes, got {size}"
+            ))
+            .into());
+        }
+        let actual = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let expected = self.shasum.as_ref();
+        if let Some(expected) = expected.filter(|e| !actual.eq_ignore_ascii_case(e.trim())) {
+            return Err(Error::VerificationFailed(format!(
+                "Checksum mismatch: expected {expected}, got {actual}"
+            ))
+            .into());
+        }
+        if let Some(verifier) = &mut verifier {
+            verifier
+                .finalize()
+                .map_err(|e| Error::VerificationFailed(format!("Signature mismatch: {e}")))?;
+        }
+        Ok(actual)
+    }
+}
+
+/// Fetches the minisign signature published next to a release artifact
+pub fn fetch_signature(download_url: &str) -> Result<String> {
+    let url = format!("{download_url}.minisig");
+    String::from_utf8(fetch_bytes(&url)?).map_err(|e| {
+        Error::VerificationFailed(format!("Invalid signature from {url}: {e}")).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test vector of the minisign-verify crate, a prehashed signature over `b"test"`
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+    /// SHA-256 of `b"test"`
+    const SHASUM: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn signed() -> Integrity {
+        Integrity {
+            signature: Some(SIGNATURE.into()),
+            public_key: Some(PUBLIC_KEY),
+            ..Integrity::default()
+        }
+    }
+
+    #[test]
+    fn returns_checksum_without_expectations() {
+        assert_eq!(Integrity::default().verify(&b"test"[..]), Ok(SHASUM.into()));
+    }
+
+    #[test]
+    fn checks_size() {
+        let integrity = Integrity { size: Some(4), ..Integrity::default() };
+        assert!(integrity.verify(&b"test"[..]).is_ok());
+
+        let integrity = Integrity { size: Some(5), ..Integrity::default() };
+        let error = integrity.verify(&b"test"[..]).unwrap_err();
+        assert!(error.contains("Size mismatch: expected 5 bytes, got 4"), "{error}");
+    }
+
+    #[test]
+    fn checks_checksum() {
+        let integrity = Integrity {
+            shasum: Some(format!(" {} ", SHASUM.to_uppercase())),
+            ..Integrity::default()
+        };
+        assert!(integrity.verify(&b"test"[..]).is_ok());
+
+        let integrity = Integrity { shasum: Some(SHASUM.into()), ..Integrity::default() };
+        let error = integrity.verify(&b"Test"[..]).unwrap_err();
+        assert!(error.contains("Checksum mismatch"), "{error}");
+    }
+
+    #[test]
+    fn rejects_pinned_key_without_signature() {
+        let integrity = Integrity { public_key: Some(PUBLIC_KEY), ..Integrity::default() };
+        let error = integrity.verify(&b"test"[..]).unwrap_err();
+        assert!(error.contains("No minisign signature available"), "{error}");
+    }
+
+    #[test]
+    fn verifies_signature() {
+        assert_eq!(signed().verify(&b"test"[..]), Ok(SHASUM.into()));
+    }
+
+    #[test]
+    fn rejects_signature_over_tampered_bytes() {
+        for data in [&b"Test"[..], b"test\n", b""] {
+            let error = signed().verify(data).unwrap_err();
+            assert!(error.contains("Signature mismatch"), "{error}");
+        }
+    }
+
+    #[test]
+    fn rejects_signature_of_another_key() {
+        let integrity = Integrity { public_key: Some(ZIGTOOLS_PUBLIC_KEY), ..signed() };
+        let error = integrity.verify(&b"test"[..]).unwrap_err();
+        assert!(error.contains("Signature mismatch"), "{error}");
+    }
+
+    #[test]
+    fn rejects_malformed_signature() {
+        let integrity = Integrity { signature: Some("not a signature".into()), ..signed() };
+        let error = integrity.verify(&b"test"[..]).unwrap_err();
+        assert!(error.contains("Invalid signature"), "{error}");
+    }
+}
@@ -0,0 +1,400 @@
+use crate::{
+    error::{Error, Result},
+    toolchain,
+};
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use zed_extension_api::{
+    SlashCommandArgumentCompletion, SlashCommandOutput, SlashCommandOutputSection,
+};
+
+/// A `pub` declaration in a Zig source file
+#[derive(Debug, Clone)]
+struct Decl {
+    name: String,
+    /// "fn", "const" or "var"
+    kind: &'static str,
+    /// Offset of the declaration, after its indentation
+    start: usize,
+    /// Brace depth the declaration sits at
+    depth: usize,
+    /// Everything up to the body or the terminating `;`
+    signature: String,
+    docs: Vec<String>,
+    /// Contents of the function body or container, without the braces
+    body: Option<Range<usize>>,
+    /// Value of a `const`/`var`, without the trailing `;`
+    value: String,
+}
+
+/// A parsed Zig source file of the standard library
+struct SourceFile {
+    path: PathBuf,
+    source: String,
+    /// `source` with comments, strings and character literals blanked out
+    code: String,
+}
+
+impl SourceFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        let source = fs::read_to_string(&path)
+            .map_err(|e| Error::FileSystem(format!("Failed to read {}: {e}", path.display())))?;
+        let code = mask(&source);
+        Ok(Self { path, source, code })
+    }
+
+    fn whole(&self) -> Range<usize> {
+        0 .. self.source.len()
+    }
+
+    /// Every `pub` declaration in the file regardless of nesting
+    fn all_decls(&self) -> Vec<Decl> {
+        let mut decls = Vec::new();
+        let mut depth = 0;
+        let mut offset = 0;
+
+        for line in self.code.split_inclusive('\n') {
+            if let Some(decl) = self.decl_at(offset, depth) {
+                decls.push(decl);
+            }
+            offset += line.len();
+            depth += line.matches('{').count();
+            depth = depth.saturating_sub(line.matches('}').count());
+        }
+        decls
+    }
+
+    /// Outermost `pub` declarations in `range`
+    fn decls(&self, range: Range<usize>) -> Vec<Decl> {
+        let mut decls = self.all_decls();
+        decls.retain(|decl| range.contains(&decl.start));
+        let min_depth = decls.iter().map(|decl| decl.depth).min().unwrap_or_default();
+        decls.retain(|decl| decl.depth == min_depth);
+        decls
+    }
+
+    fn decl_at(&self, line_start: usize, depth: usize) -> Option<Decl> {
+        let line = self.code[line_start ..].lines().next()?;
+        let start = line_start + (line.len() - line.trim_start().len());
+
+        let mut words = line.split_whitespace();
+        if words.next()? != "pub" {
+            return None;
+        }
+        let kind = words.find_map(|word| match word {
+            "fn" => Some("fn"),
+            "const" => Some("const"),
+            "var" => Some("var"),
+            "extern" | "export" | "inline" | "noinline" | "threadlocal" => None,
+            _ => Some(""),
+        })?;
+        if kind.is_empty() {
+            return None;
+        }
+
+        let rest = &self.code[start ..];
+        let name_start = rest.find(&format!("{kind} "))? + kind.len() + 1;
+        let name = rest[name_start ..]
+            .trim_start()
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .next()
+            .filter(|name| !name.is_empty())?
+            .to_string();
+
+        let (end, body) = self.extent(start, kind == "fn");
+        // One line values like `error{OutOfMemory}` are shown in full, containers are cut off at
+        // their opening brace.
+        let signature_end = match &body {
+            Some(body) if kind == "fn" || self.source[start .. end].contains('\n') => {
+                body.start - 1
+            }
+            _ => end + 1,
+        };
+        let indent = &self.source[line_start .. start];
+        let signature = self.source[start .. signature_end.min(self.source.len())]
+            .trim_end()
+            .replace(&format!("\n{indent}"), "\n");
+        let value = match self.source[start .. end].split_once('=') {
+            Some((_, value)) if kind != "fn" => value.trim().to_string(),
+            _ => String::new(),
+        };
+
+        Some(Decl { name, kind, start, depth, signature, docs: self.docs(start), body, value })
+    }
+
+    /// End of the declaration starting at `start` and the range inside its braces
+    fn extent(&self, start: usize, is_fn: bool) -> (usize, Option<Range<usize>>) {
+        let (mut parens, mut braces) = (0usize, 0usize);
+        let mut body_start = None;
+
+        for (i, c) in self.code[start ..].char_indices() {
+            let i = start + i;
+            match c {
+                '(' | '[' => parens += 1,
+                ')' | ']' => parens = parens.saturating_sub(1),
+                '{' => {
+                    if braces == 0 && parens == 0 && body_start.is_none() {
+                        body_start = Some(i + 1);
+                    }
+                    braces += 1;
+                }
+                '}' => {
+                    braces = braces.saturating_sub(1);
+                    if braces == 0 && is_fn {
+                        if let Some(body_start) = body_start {
+                            return (i + 1, Some(body_start .. i));
+                        }
+                    }
+                }
+                ';' if braces == 0 && parens == 0 => {
+                    let body = body_start.map(|body_start| body_start .. self.closing_brace(i));
+                    return (i, body);
+                }
+                _ => {}
+            }
+        }
+        (self.source.len(), None)
+    }
+
+    /// Position of the last `}` before `end`
+    fn closing_brace(&self, end: usize) -> usize {
+        self.code[.. end].rfind('}').unwrap_or(end)
+    }
+
+    /// `///` comment lines right above `start`
+    fn docs(&self, start: usize) -> Vec<String> {
+        let line_start = self.source[.. start].rfind('\n').map_or(0, |i| i + 1);
+        let mut docs = self.source[.. line_start]
+            .lines()
+            .rev()
+            .map(str::trim)
+            .take_while(|line| line.starts_with("///"))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        docs.reverse();
+        docs
+    }
+
+    /// `//!` comments at the top of the file
+    fn file_docs(&self) -> Vec<String> {
+        self.source
+            .lines()
+            .map(str::trim)
+            .take_while(|line| line.starts_with("//!") || line.is_empty())
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Range the members of `decl` are declared in
+    ///
+    /// Wrappers like `pub fn ArrayList(comptime T: type) type { return ArrayListAligned(T); }` and
+    /// aliases like `pub const Foo = Bar;` are followed to the container they name.
+    fn members(&self, decl: &Decl) -> Range<usize> {
+        let range = decl.body.clone().unwrap_or(decl.start .. decl.start);
+        if !self.decls(range.clone()).is_empty() {
+            return range;
+        }
+
+        let target = match decl.kind {
+            "fn" => self.code[range.clone()].trim().strip_prefix("return "),
+            _ => Some(decl.value.as_str()),
+        };
+        let target = target.and_then(|target| {
+            let name = target.split(|c: char| !(c.is_alphanumeric() || c == '_')).next()?;
+            let first = name.chars().next()?;
+            (first.is_alphabetic() || first == '_').then_some(name)
+        });
+
+        target
+            .filter(|&target| target != decl.name)
+            .and_then(|target| self.all_decls().into_iter().find(|decl| decl.name == target))
+            .and_then(|target| target.body)
+            .unwrap_or(range)
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.source[.. offset].matches('\n').count() + 1
+    }
+}
+
+/// Where a path like `std.mem.Allocator` leads to
+struct Target {
+    file: SourceFile,
+    range: Range<usize>,
+    decl: Option<Decl>,
+}
+
+/// Resolves `std.a.b` through `@import`s and nested containers
+fn resolve(std_dir: &Path, path: &str) -> Result<Target> {
+    let mut segments = path.split('.').filter(|segment| !segment.is_empty());
+    if segments.next() != Some("std") {
+        return Err(Error::Configuration {
+            message: format!("{path} is not part of the standard library"),
+            fix: "Start the declaration with \"std.\", e.g. std.ArrayList.append".into(),
+        }
+        .into());
+    }
+
+    let file = SourceFile::open(std_dir.join("std.zig"))?;
+    let target = Target { range: file.whole(), file, decl: None };
+    segments.try_fold(target, |target, segment| step(target, segment, path))
+}
+
+fn step(target: Target, segment: &str, path: &str) -> Result<Target> {
+    let Target { file, range, .. } = target;
+
+    // Generic types hide their members in a returned struct, possibly one defined elsewhere in
+    // the file, so fall back to searching the whole file.
+    let decl = file
+        .decls(range)
+        .into_iter()
+        .find(|decl| decl.name == segment)
+        .or_else(|| file.all_decls().into_iter().find(|decl| decl.name == segment))
+        .ok_or_else(|| format!("No declaration named {segment} found while looking up {path}"))?;
+
+    match decl.value.strip_prefix("@import(\"").map(str::to_string) {
+        Some(import) => {
+            let (import, rest) = import.split_once("\")").unwrap_or((&import, ""));
+            let dir = file.path.parent().unwrap_or(Path::new("."));
+            let imported = SourceFile::open(dir.join(import))?;
+            let target = Target { range: imported.whole(), file: imported, decl: Some(decl) };
+            rest.trim_end_matches(';')
+                .split('.')
+                .filter(|segment| !segment.is_empty())
+                .try_fold(target, |target, segment| step(target, segment, path))
+        }
+        None => {
+            let range = file.members(&decl);
+            Ok(Target { file, range, decl: Some(decl) })
+        }
+    }
+}
+
+/// Standard library of the newest Zig compiler installed by the extension
+///
+/// Completions can't wait for a download, the std is installed the first time `/zigdoc` runs.
+fn std_dir() -> Result<PathBuf> {
+    let toolchain = toolchain::find_installed().ok_or_else(|| Error::Configuration {
+        message: "The Zig standard library hasn't been downloaded yet".into(),
+        fix: "Run /zigdoc std once to download it".into(),
+    })?;
+    Ok(toolchain.zig_lib_path().join("std"))
+}
+
+/// Output of `/zigdoc <declaration>`, looked up in the std of `zig_lib_dir`
+pub fn run(args: &[String], zig_lib_dir: &Path) -> Result<SlashCommandOutput> {
+    let path = args.join("");
+    let path = path.trim();
+    if path.is_empty() {
+        return Err("Pass a declaration, e.g. /zigdoc std.ArrayList.append".into());
+    }
+
+    let std_dir = zig_lib_dir.join("std");
+    let Target { file, range, decl } = resolve(&std_dir, path)?;
+    let location = file.path.strip_prefix(&std_dir).unwrap_or(&file.path).display().to_string();
+
+    let mut text = String::new();
+    match decl.filter(|_| range != file.whole()) {
+        Some(decl) => {
+            let line = file.line(decl.start);
+            text.push_str(&format!("{path} (std/{location}:{line})\n```zig\n"));
+            for line in &decl.docs {
+                text.push_str(&format!("{line}\n"));
+            }
+            text.push_str(&format!("{}\n", decl.signature));
+        }
+        // Namespaces that are whole files show the file docs and their members.
+        None => {
+            text.push_str(&format!("{path} (std/{location})\n```zig\n"));
+            for line in file.file_docs() {
+                text.push_str(&format!("{line}\n"));
+            }
+            for decl in file.decls(range) {
+                text.push_str(&format!("{}\n", decl.signature.lines().next().unwrap_or_default()));
+            }
+        }
+    }
+    text.push_str("```\n");
+
+    Ok(SlashCommandOutput {
+        sections: vec![SlashCommandOutputSection {
+            range: (0 .. text.len()).into(),
+            label: format!("zigdoc: {path}"),
+        }],
+        text,
+    })
+}
+
+/// Completions for the members of the namespace typed so far
+pub fn complete(args: &[String]) -> Result<Vec<SlashCommandArgumentCompletion>> {
+    let query = args.join("");
+    let Some((namespace, partial)) = query.rsplit_once('.') else {
+        return Ok(vec![SlashCommandArgumentCompletion {
+            label: "std".into(),
+            new_text: "std.".into(),
+            run_command: false,
+        }]);
+    };
+
+    let Target { file, range, .. } = resolve(&std_dir()?, namespace)?;
+    Ok(file
+        .decls(range)
+        .into_iter()
+        .filter(|decl| decl.name.starts_with(partial))
+        .map(|decl| SlashCommandArgumentCompletion {
+            label: format!("{} {}", decl.kind, decl.name),
+            new_text: format!("{namespace}.{}", decl.name),
+            run_command: decl.kind == "fn",
+        })
+        .collect())
+}
+
+/// Blanks out comments, strings and character literals so braces and keywords can be matched
+/// without a full parser
+fn mask(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut code = bytes.to_vec();
+    let mut i = 0;
+
+    let blank = |code: &mut Vec<u8>, range: Range<usize>| {
+        for byte in &mut code[range] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+    let line_end = |from: usize| {
+        bytes[from ..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |end| from + end)
+    };
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                let end = line_end(i);
+                blank(&mut code, i .. end);
+                i = end;
+            }
+            b'\\' if bytes.get(i + 1) == Some(&b'\\') => {
+                let end = line_end(i);
+                blank(&mut code, i .. end);
+                i = end;
+            }
+            quote @ (b'"' | b'\'') => {
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != quote && bytes[end] != b'\n' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                let end = (end + 1).min(bytes.len());
+                blank(&mut code, i + 1 .. end.saturating_sub(1).max(i + 1));
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    String::from_utf8(code).unwrap_or_default()
+}